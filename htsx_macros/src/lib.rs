@@ -0,0 +1,177 @@
+use proc_macro::{
+    TokenStream,
+    TokenTree,
+    Literal,
+    Span,
+};
+use s_expression_parser::{
+    File as SFile,
+    Location,
+};
+use htsx::{
+    css::{
+        Item as CssItem,
+        Error as CssError,
+        Constants,
+        write_items as write_css,
+    },
+    render::RenderOptions,
+};
+use std::ops::Range;
+
+
+type Position=(usize,usize);
+
+
+/// Converts CSSX to CSS at compile time and expands to a `&'static str` of the generated CSS.
+///
+/// The CSSX can be written as tokens, but those have to get through Rust's tokenizer first, so things like `1em`
+/// and `#1e2` don't work. Pass a string literal instead to use any CSSX:
+/// ```
+/// const CSS:&str=htsx_macros::cssx!(r#"(.a (margin 1em) (color #1e2))"#);
+/// assert_eq!(CSS,".a {\n    margin: 1em;\n    color: #1e2;\n}\n");
+/// ```
+/// Errors are reported as compile errors on the offending token, or on the literal with the line and column.
+/// Constants from `(define ...)` are visible to the whole invocation, but `(@use ...)` isn't supported.
+/// ```compile_fail
+/// const CSS:&str=htsx_macros::cssx!((.a (width red)));
+/// ```
+#[proc_macro]
+pub fn cssx(input:TokenStream)->TokenStream {
+    let trees=input.into_iter().collect::<Vec<_>>();
+    let sources=match trees.as_slice() {
+        [TokenTree::Literal(literal)] if literal.to_string().ends_with(['"','#'])=>{
+            match string_value(&literal.to_string()) {
+                Some(text)=>vec![Source{text,span:literal.span(),tokens:Vec::new()}],
+                None=>return compile_error(literal.span(),"Invalid CSSX: only plain and raw string literals are supported".into()),
+            }
+        },
+        _=>trees.iter().map(Source::from_tree).collect(),
+    };
+    let mut files=Vec::new();
+    for source in sources.iter() {
+        match SFile::parse_file(&source.text) {
+            Ok(file)=>files.push((source,file)),
+            Err(err)=>return source.error(err.location,"Invalid CSSX: syntax error"),
+        }
+    }
+    let mut items=Vec::new();
+    for (source,file) in files.iter() {
+        for i in file.items.iter() {
+            match CssItem::try_from(i) {
+                Ok(item)=>items.push((*source,item)),
+                Err(err)=>return source.css_error(err),
+            }
+        }
+    }
+    let mut constants=Constants::default();
+    for (source,item) in items.iter() {
+        if let Err(err)=constants.add_items(std::slice::from_ref(item)) {
+            return source.css_error(err);
+        }
+    }
+    for (source,item) in items.iter_mut() {
+        if let Err(err)=item.resolve(&constants) {
+            return source.css_error(err);
+        }
+    }
+    let items=items.into_iter().map(|(_,item)|item).collect::<Vec<_>>();
+    let mut out=String::new();
+    write_css(&mut out,&items,&RenderOptions::default(),0).unwrap();
+    TokenTree::Literal(Literal::string(&out)).into()
+}
+struct Source {
+    text:String,
+    span:Span,
+    tokens:Vec<(Range<Position>,Span)>,
+}
+impl Source {
+    fn from_tree(tree:&TokenTree)->Self {
+        let span=tree.span();
+        // keep the original text so things like `#fff` and `200%` make it through untouched
+        let Some(text)=span.source_text() else {
+            return Source{text:tree.to_string(),span,tokens:Vec::new()};
+        };
+        let mut tokens=Vec::new();
+        add_tokens(tree,position(span.start()),&mut tokens);
+        Source{text,span,tokens}
+    }
+    fn error(&self,location:Location,msg:&str)->TokenStream {
+        if self.tokens.is_empty() {
+            return compile_error(self.span,format!("{} at line {}, column {}",msg,location.line+1,location.column+1));
+        }
+        let span=self.tokens.iter()
+            .find(|(range,_)|range.end>(location.line,location.column))
+            .map_or(self.span,|(_,span)|*span);
+        compile_error(span,msg.into())
+    }
+    fn css_error(&self,err:CssError)->TokenStream {
+        self.error(err.start,&format!("Invalid CSSX: {:?}",err.err_type))
+    }
+}
+fn position(span:Span)->Position {
+    (span.line()-1,span.column())
+}
+fn add_tokens(tree:&TokenTree,base:Position,tokens:&mut Vec<(Range<Position>,Span)>) {
+    // relative to the start of the tree, like the parser's locations
+    let relative=|span:Span|{
+        let relative=|(line,column):Position|match line==base.0 {
+            true=>(0,column.saturating_sub(base.1)),
+            false=>(line-base.0,column),
+        };
+        relative(position(span.start()))..relative(position(span.end()))
+    };
+    match tree {
+        TokenTree::Group(group)=>{
+            tokens.push((relative(group.span_open()),group.span_open()));
+            for tree in group.stream() {
+                add_tokens(&tree,base,tokens);
+            }
+            tokens.push((relative(group.span_close()),group.span_close()));
+        },
+        _=>tokens.push((relative(tree.span()),tree.span())),
+    }
+}
+fn string_value(literal:&str)->Option<String> {
+    if let Some(raw)=literal.strip_prefix('r') {
+        let hashes=raw.len()-raw.trim_start_matches('#').len();
+        return Some(raw.get(hashes+1..raw.len()-hashes-1)?.to_string());
+    }
+    let mut chars=literal.strip_prefix('"')?.strip_suffix('"')?.chars().peekable();
+    let mut value=String::new();
+    while let Some(c)=chars.next() {
+        if c!='\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n'=>value.push('\n'),
+            'r'=>value.push('\r'),
+            't'=>value.push('\t'),
+            '0'=>value.push('\0'),
+            'x'=>{
+                let code=chars.next()?.to_digit(16)?*16+chars.next()?.to_digit(16)?;
+                value.push(char::from_u32(code)?);
+            },
+            'u'=>{
+                chars.next();
+                let code=chars.by_ref().take_while(|c|*c!='}').filter(|c|*c!='_').collect::<String>();
+                value.push(char::from_u32(u32::from_str_radix(&code,16).ok()?)?);
+            },
+            '\n'=>while chars.next_if(|c|c.is_whitespace()).is_some() {},
+            c=>value.push(c),
+        }
+    }
+    Some(value)
+}
+fn compile_error(span:Span,msg:String)->TokenStream {
+    format!("compile_error!({:?})",msg)
+        .parse::<TokenStream>()
+        .unwrap()
+        .into_iter()
+        .map(|mut tree|{
+            tree.set_span(span);
+            tree
+        })
+        .collect()
+}
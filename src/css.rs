@@ -4,9 +4,28 @@ use s_expression_parser::{
 };
 use std::{
     fmt::{
+        Display,
+        Formatter,
         Result as FmtResult,
         Write,
     },
+    collections::{
+        HashSet,
+        HashMap,
+    },
+};
+use crate::{
+    render::RenderOptions,
+    targets::{
+        Targets,
+        PREFIXED_PROPERTIES,
+        PREFIXED_PSEUDOS,
+        PREFIXED_KEYFRAMES,
+        LAB_COLORS,
+        LOGICAL_PROPERTIES,
+        CLAMP,
+        MIN_MAX,
+    },
 };
 
 
@@ -21,9 +40,755 @@ pub enum ErrorType {
     ExpectedFontValue,
     InvalidAttribute,
     EmptyList,
+    ExpectedSimpleSelector,
+    UnknownPseudoClass,
+    UnknownPseudoElement,
+    InvalidAttributeSelector,
+    UndefinedConstant,
+    InvalidMixin,
+    UndefinedMixin,
+    WrongArgumentCount,
+    MixinRecursion,
+    IncompatibleUnits,
+    DivisionByZero,
+    InvalidColor,
+    InvalidValue,
+    UnknownDescriptor,
+    MissingDescriptor,
+    UnknownMarginBox,
+    InvalidMediaQuery,
+    InvalidSupportsCondition,
+    PercentOutOfRange,
+    NotAllowedInKeyframe,
     // UnknownName,
 }
-#[derive(Debug)]
+const PSEUDO_CLASSES:&[&str]=&[
+    "active","any-link","autofill","blank","checked","current","default","defined","disabled","empty",
+    "enabled","first","first-child","first-of-type","focus","focus-visible","focus-within","fullscreen",
+    "future","host","hover","in-range","indeterminate","invalid","last-child","last-of-type","left","link",
+    "local-link","modal","only-child","only-of-type","open","optional","out-of-range","past","paused",
+    "picture-in-picture","placeholder-shown","playing","popover-open","read-only","read-write","required",
+    "right","root","scope","target","target-within","user-invalid","user-valid","valid","visited",
+    // CSS2 pseudo-elements may still be written with one colon
+    "after","before","first-letter","first-line",
+];
+const PSEUDO_CLASS_SELECTOR_FUNCTIONS:&[&str]=&["not","is","where","has","host","host-context"];
+const PSEUDO_CLASS_FUNCTIONS:&[&str]=&["nth-child","nth-last-child","nth-of-type","nth-last-of-type","lang","dir","state"];
+const PSEUDO_ELEMENTS:&[&str]=&[
+    "after","backdrop","before","cue","file-selector-button","first-letter","first-line","grammar-error",
+    "marker","placeholder","selection","spelling-error","target-text","view-transition",
+];
+const PSEUDO_ELEMENT_SELECTOR_FUNCTIONS:&[&str]=&["slotted","cue"];
+const PSEUDO_ELEMENT_FUNCTIONS:&[&str]=&[
+    "part","highlight","view-transition-group","view-transition-image-pair","view-transition-old",
+    "view-transition-new",
+];
+const ATTRIBUTE_OPERATORS:&[&str]=&["=","~=","|=","^=","$=","*="];
+const MAX_MIXIN_DEPTH:usize=32;
+const LENGTH_UNITS:&[&str]=&["px","em","rem","ex","ch","vw","vh","vmin","vmax","cm","mm","q","in","pt","pc"];
+const OTHER_LENGTH_UNITS:&[&str]=&[
+    "cap","ic","lh","rlh","rex","rcap","rch","ric","vi","vb","svw","svh","svi","svb","svmin","svmax",
+    "lvw","lvh","lvi","lvb","lvmin","lvmax","dvw","dvh","dvi","dvb","dvmin","dvmax","cqw","cqh","cqi","cqb",
+    "cqmin","cqmax",
+];
+const NESTED_SELECTOR_STARTS:&[char]=&['&','.','#','*',':','['];
+const MATH_FUNCTIONS:&[&str]=&["calc","min","max","clamp"];
+const KEEP_ZERO_UNITS:&[&str]=&["flex","flex-basis"];
+const VERBATIM_FUNCTIONS:&[&str]=&["url","src","element","attr","local","format","tech"];
+const SPACE_SEPARATED_FUNCTIONS:&[&str]=&["hwb","lab","lch","oklab","oklch","color"];
+// names that are also keywords, colors, or properties, like `repeat` or `scale`, need `(fn name ...)`
+const CSS_FUNCTIONS:&[&str]=&[
+    "rgb","rgba","hsl","hsla","hwb","lab","lch","oklab","oklch","color-mix","light-dark",
+    "calc","min","max","clamp","abs","sign","sin","cos","asin","acos","atan","atan2",
+    "pow","sqrt","hypot","log","exp",
+    "var","env","attr","url","image-set","cross-fade","counters",
+    "linear-gradient","radial-gradient","conic-gradient","repeating-linear-gradient","repeating-radial-gradient",
+    "repeating-conic-gradient",
+    "translatex","translatey","translatez","translate3d","scalex","scaley","scalez","scale3d",
+    "rotatex","rotatey","rotatez","rotate3d","skew","skewx","skewy","matrix","matrix3d",
+    "blur","brightness","contrast","drop-shadow","grayscale","hue-rotate","saturate","sepia",
+    "cubic-bezier","steps","minmax","fit-content","polygon","xywh",
+];
+
+
+fn split_number(text:&str)->Option<(&str,&str)> {
+    let end=text.find(|c:char|!(c.is_ascii_digit()||c=='.'||c=='+'||c=='-')).unwrap_or(text.len());
+    let (number,unit)=text.split_at(end);
+    number.parse::<f64>().ok()?;
+    Some((number,unit))
+}
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum UnitKind {
+    Number,
+    Percent,
+    Length,
+    Time,
+    Angle,
+    Frequency,
+    Resolution,
+    Flex,
+    Other,
+}
+impl UnitKind {
+    fn of(unit:&str)->Self {
+        let unit=unit.to_ascii_lowercase();
+        let unit=unit.as_str();
+        if unit.is_empty() {
+            Self::Number
+        } else if unit=="%" {
+            Self::Percent
+        } else if LENGTH_UNITS.contains(&unit)||OTHER_LENGTH_UNITS.contains(&unit) {
+            Self::Length
+        } else {
+            match unit {
+                "s"|"ms"=>Self::Time,
+                "deg"|"grad"|"rad"|"turn"=>Self::Angle,
+                "hz"|"khz"=>Self::Frequency,
+                "dpi"|"dpcm"|"dppx"|"x"=>Self::Resolution,
+                "fr"=>Self::Flex,
+                _=>Self::Other,
+            }
+        }
+    }
+    fn add(self,other:Self)->Option<Self> {
+        match (self,other) {
+            (a,b) if a==b=>Some(a),
+            (Self::Percent,Self::Number)|(Self::Number,Self::Percent)=>None,
+            (Self::Percent,other)|(other,Self::Percent)=>Some(other),
+            _=>None,
+        }
+    }
+}
+
+
+const FONT_FACE_DESCRIPTORS:&[&str]=&[
+    "font-weight","font-style","font-display","unicode-range","font-stretch","font-feature-settings",
+    "font-variation-settings","font-language-override","font-named-instance","ascent-override","descent-override",
+    "line-gap-override","size-adjust",
+];
+const PAGE_MARGIN_BOXES:&[&str]=&[
+    "@top-left-corner","@top-left","@top-center","@top-right","@top-right-corner","@bottom-left-corner",
+    "@bottom-left","@bottom-center","@bottom-right","@bottom-right-corner","@left-top","@left-middle",
+    "@left-bottom","@right-top","@right-middle","@right-bottom",
+];
+const MEDIA_TYPES:&[&str]=&["all","print","screen","tty","tv","projection","handheld","braille","embossed","aural","speech"];
+const RANGE_OPERATORS:&[&str]=&["<","<=",">",">=","="];
+const FONT_DISPLAY_KEYWORDS:&[&str]=&["auto","block","swap","fallback","optional"];
+const LOGICAL_TO_PHYSICAL:&[(&str,&[&str])]=&[
+    ("margin-inline-start",&["margin-left"]),("margin-inline-end",&["margin-right"]),
+    ("margin-block-start",&["margin-top"]),("margin-block-end",&["margin-bottom"]),
+    ("margin-inline",&["margin-left","margin-right"]),("margin-block",&["margin-top","margin-bottom"]),
+    ("padding-inline-start",&["padding-left"]),("padding-inline-end",&["padding-right"]),
+    ("padding-block-start",&["padding-top"]),("padding-block-end",&["padding-bottom"]),
+    ("padding-inline",&["padding-left","padding-right"]),("padding-block",&["padding-top","padding-bottom"]),
+    ("inset-inline-start",&["left"]),("inset-inline-end",&["right"]),
+    ("inset-block-start",&["top"]),("inset-block-end",&["bottom"]),
+    ("inset-inline",&["left","right"]),("inset-block",&["top","bottom"]),
+    ("inline-size",&["width"]),("block-size",&["height"]),
+    ("min-inline-size",&["min-width"]),("min-block-size",&["min-height"]),
+    ("max-inline-size",&["max-width"]),("max-block-size",&["max-height"]),
+    ("border-inline-start",&["border-left"]),("border-inline-end",&["border-right"]),
+    ("border-block-start",&["border-top"]),("border-block-end",&["border-bottom"]),
+    ("border-start-start-radius",&["border-top-left-radius"]),("border-start-end-radius",&["border-top-right-radius"]),
+    ("border-end-start-radius",&["border-bottom-left-radius"]),("border-end-end-radius",&["border-bottom-right-radius"]),
+];
+const PROPERTIES:&[&str]=&[
+    "accent-color", "align-content", "align-items", "align-self", "alignment-baseline", "all", "anchor-name",
+    "animation", "animation-composition", "animation-delay", "animation-direction", "animation-duration",
+    "animation-fill-mode", "animation-iteration-count", "animation-name", "animation-play-state", "animation-range",
+    "animation-timeline", "animation-timing-function", "appearance", "aspect-ratio", "backdrop-filter",
+    "backface-visibility", "background", "background-attachment", "background-blend-mode", "background-clip",
+    "background-color", "background-image", "background-origin", "background-position", "background-position-x",
+    "background-position-y", "background-repeat", "background-size", "baseline-shift", "block-size", "border",
+    "border-block", "border-block-color", "border-block-end", "border-block-end-color", "border-block-end-style",
+    "border-block-end-width", "border-block-start", "border-block-start-color", "border-block-start-style",
+    "border-block-start-width", "border-block-style", "border-block-width", "border-bottom", "border-bottom-color",
+    "border-bottom-left-radius", "border-bottom-right-radius", "border-bottom-style", "border-bottom-width",
+    "border-collapse", "border-color", "border-end-end-radius", "border-end-start-radius", "border-image",
+    "border-image-outset", "border-image-repeat", "border-image-slice", "border-image-source", "border-image-width",
+    "border-inline", "border-inline-color", "border-inline-end", "border-inline-end-color", "border-inline-end-style",
+    "border-inline-end-width", "border-inline-start", "border-inline-start-color", "border-inline-start-style",
+    "border-inline-start-width", "border-inline-style", "border-inline-width", "border-left", "border-left-color",
+    "border-left-style", "border-left-width", "border-radius", "border-right", "border-right-color",
+    "border-right-style", "border-right-width", "border-spacing", "border-start-end-radius",
+    "border-start-start-radius", "border-style", "border-top", "border-top-color", "border-top-left-radius",
+    "border-top-right-radius", "border-top-style", "border-top-width", "border-width", "bottom", "box-decoration-break",
+    "box-shadow", "box-sizing", "break-after", "break-before", "break-inside", "caption-side", "caret-color", "clear",
+    "clip", "clip-path", "clip-rule", "color", "color-interpolation", "color-scheme", "column-count", "column-fill",
+    "column-gap", "column-rule", "column-rule-color", "column-rule-style", "column-rule-width", "column-span",
+    "column-width", "columns", "contain", "contain-intrinsic-block-size", "contain-intrinsic-height",
+    "contain-intrinsic-inline-size", "contain-intrinsic-size", "contain-intrinsic-width", "container", "container-name",
+    "container-type", "content", "content-visibility", "counter-increment", "counter-reset", "counter-set", "cursor",
+    "cx", "cy", "d", "direction", "display", "dominant-baseline", "empty-cells", "fill", "fill-opacity", "fill-rule",
+    "filter", "flex", "flex-basis", "flex-direction", "flex-flow", "flex-grow", "flex-shrink", "flex-wrap", "float",
+    "flood-color", "flood-opacity", "font", "font-family", "font-feature-settings", "font-kerning",
+    "font-language-override", "font-optical-sizing", "font-palette", "font-size", "font-size-adjust", "font-stretch",
+    "font-style", "font-synthesis", "font-variant", "font-variant-alternates", "font-variant-caps",
+    "font-variant-east-asian", "font-variant-emoji", "font-variant-ligatures", "font-variant-numeric",
+    "font-variant-position", "font-variation-settings", "font-weight", "forced-color-adjust", "gap", "grid",
+    "grid-area", "grid-auto-columns", "grid-auto-flow", "grid-auto-rows", "grid-column", "grid-column-end",
+    "grid-column-gap", "grid-column-start", "grid-gap", "grid-row", "grid-row-end", "grid-row-gap", "grid-row-start",
+    "grid-template", "grid-template-areas", "grid-template-columns", "grid-template-rows", "hanging-punctuation",
+    "height", "hyphenate-character", "hyphens", "image-orientation", "image-rendering", "initial-letter", "inline-size",
+    "inset", "inset-block", "inset-block-end", "inset-block-start", "inset-inline", "inset-inline-end",
+    "inset-inline-start", "isolation", "justify-content", "justify-items", "justify-self", "left", "letter-spacing",
+    "lighting-color", "line-break", "line-clamp", "line-height", "list-style", "list-style-image",
+    "list-style-position", "list-style-type", "margin", "margin-block", "margin-block-end", "margin-block-start",
+    "margin-bottom", "margin-inline", "margin-inline-end", "margin-inline-start", "margin-left", "margin-right",
+    "margin-top", "marker", "marker-end", "marker-mid", "marker-start", "mask", "mask-border", "mask-clip",
+    "mask-composite", "mask-image", "mask-mode", "mask-origin", "mask-position", "mask-repeat", "mask-size",
+    "mask-type", "math-depth", "math-style", "max-block-size", "max-height", "max-inline-size", "max-width",
+    "min-block-size", "min-height", "min-inline-size", "min-width", "mix-blend-mode", "object-fit", "object-position",
+    "offset", "offset-anchor", "offset-distance", "offset-path", "offset-position", "offset-rotate", "opacity", "order",
+    "orphans", "outline", "outline-color", "outline-offset", "outline-style", "outline-width", "overflow",
+    "overflow-anchor", "overflow-block", "overflow-clip-margin", "overflow-inline", "overflow-wrap", "overflow-x",
+    "overflow-y", "overscroll-behavior", "overscroll-behavior-block", "overscroll-behavior-inline",
+    "overscroll-behavior-x", "overscroll-behavior-y", "padding", "padding-block", "padding-block-end",
+    "padding-block-start", "padding-bottom", "padding-inline", "padding-inline-end", "padding-inline-start",
+    "padding-left", "padding-right", "padding-top", "page", "page-break-after", "page-break-before",
+    "page-break-inside", "paint-order", "perspective", "perspective-origin", "place-content", "place-items",
+    "place-self", "pointer-events", "position", "position-anchor", "print-color-adjust", "quotes", "r", "resize",
+    "right", "rotate", "row-gap", "ruby-align", "ruby-position", "rx", "ry", "scale", "scroll-behavior",
+    "scroll-margin", "scroll-margin-block", "scroll-margin-block-end", "scroll-margin-block-start",
+    "scroll-margin-bottom", "scroll-margin-inline", "scroll-margin-inline-end", "scroll-margin-inline-start",
+    "scroll-margin-left", "scroll-margin-right", "scroll-margin-top", "scroll-padding", "scroll-padding-block",
+    "scroll-padding-block-end", "scroll-padding-block-start", "scroll-padding-bottom", "scroll-padding-inline",
+    "scroll-padding-inline-end", "scroll-padding-inline-start", "scroll-padding-left", "scroll-padding-right",
+    "scroll-padding-top", "scroll-snap-align", "scroll-snap-stop", "scroll-snap-type", "scroll-timeline",
+    "scroll-timeline-axis", "scroll-timeline-name", "scrollbar-color", "scrollbar-gutter", "scrollbar-width",
+    "shape-image-threshold", "shape-margin", "shape-outside", "shape-rendering", "stop-color", "stop-opacity", "stroke",
+    "stroke-dasharray", "stroke-dashoffset", "stroke-linecap", "stroke-linejoin", "stroke-miterlimit", "stroke-opacity",
+    "stroke-width", "tab-size", "table-layout", "text-align", "text-align-last", "text-anchor", "text-combine-upright",
+    "text-decoration", "text-decoration-color", "text-decoration-line", "text-decoration-skip-ink",
+    "text-decoration-style", "text-decoration-thickness", "text-emphasis", "text-emphasis-color",
+    "text-emphasis-position", "text-emphasis-style", "text-indent", "text-justify", "text-orientation", "text-overflow",
+    "text-rendering", "text-shadow", "text-size-adjust", "text-transform", "text-underline-offset",
+    "text-underline-position", "text-wrap", "text-wrap-mode", "text-wrap-style", "top", "touch-action", "transform",
+    "transform-box", "transform-origin", "transform-style", "transition", "transition-behavior", "transition-delay",
+    "transition-duration", "transition-property", "transition-timing-function", "translate", "unicode-bidi",
+    "user-select", "vector-effect", "vertical-align", "view-timeline", "view-timeline-axis", "view-timeline-inset",
+    "view-timeline-name", "view-transition-name", "visibility", "white-space", "white-space-collapse", "widows",
+    "width", "will-change", "word-break", "word-spacing", "word-wrap", "writing-mode", "x", "y", "z-index", "zoom",
+];
+const GLOBAL_KEYWORDS:&[&str]=&["inherit","initial","unset","revert","revert-layer"];
+const SIZE_KEYWORDS:&[&str]=&["auto","min-content","max-content","fit-content","stretch","none"];
+const FONT_SIZE_KEYWORDS:&[&str]=&[
+    "xx-small","x-small","small","medium","large","x-large","xx-large","xxx-large","smaller","larger","math",
+];
+const SYSTEM_COLORS:&[&str]=&[
+    "currentcolor","accentcolor","accentcolortext","activetext","buttonborder","buttonface","buttontext","canvas",
+    "canvastext","field","fieldtext","graytext","highlight","highlighttext","linktext","mark","marktext",
+    "selecteditem","selecteditemtext","visitedtext",
+];
+const BORDER_WIDTH_KEYWORDS:&[&str]=&["thin","medium","thick"];
+const COMMON_KEYWORDS:&[&str]=&[
+    "inset","outset","space","round","repeat","repeat-x","repeat-y","no-repeat","stretch","start","end","center",
+    "left","right","top","bottom","row","column","row-reverse","column-reverse","wrap","nowrap","wrap-reverse",
+    "dense","span","solid","dashed","dotted","double","groove","ridge","normal","bold","bolder","lighter","italic",
+    "oblique","ease","ease-in","ease-out","ease-in-out","linear","step-start","step-end","infinite","alternate",
+    "alternate-reverse","reverse","forwards","backwards","both","running","paused","cover","contain","fill",
+    "stroke","content-box","border-box","padding-box","text","baseline","sub","super","middle","fixed","absolute",
+    "relative","static","sticky","local","smooth","pointer","default","first","last","safe","unsafe","ltr","rtl",
+    "uppercase","lowercase","capitalize","underline","overline","line-through","ellipsis","collapse","separate",
+    "inside","outside","disc","circle","ellipse","square","decimal","transparent","invert","to","at","from","in",
+    "closest-side","closest-corner","farthest-side","farthest-corner","contrast","color","opacity","image","rect",
+    "path","element","counter","symbols","mod","rem","tan",
+];
+const OVERFLOW_KEYWORDS:&[&str]=&["visible","hidden","clip","scroll","auto","overlay"];
+const DISPLAY_KEYWORDS:&[&str]=&[
+    "block","inline","run-in","flow","flow-root","table","flex","grid","ruby","math","list-item","table-row-group",
+    "table-header-group","table-footer-group","table-row","table-cell","table-column-group","table-column",
+    "table-caption","ruby-base","ruby-text","ruby-base-container","ruby-text-container","contents","none",
+    "inline-block","inline-table","inline-flex","inline-grid","-webkit-box","-webkit-inline-box","-ms-flexbox",
+];
+const VALUE_TYPES:&[(&[&str],ValueType,usize)]=&[
+    (&["width","height","inline-size","block-size"],ValueType::Length(SIZE_KEYWORDS),1),
+    (&["min-width","min-height","min-inline-size","min-block-size"],ValueType::Length(SIZE_KEYWORDS),1),
+    (&["max-width","max-height","max-inline-size","max-block-size"],ValueType::Length(SIZE_KEYWORDS),1),
+    (&["top","right","bottom","left","inset-block-start","inset-block-end","inset-inline-start","inset-inline-end"],ValueType::Length(&["auto"]),1),
+    (&["inset"],ValueType::Length(&["auto"]),4),
+    (&["margin"],ValueType::Length(&["auto"]),4),
+    (&["margin-block","margin-inline"],ValueType::Length(&["auto"]),2),
+    (&["margin-top","margin-right","margin-bottom","margin-left","margin-block-start","margin-block-end","margin-inline-start","margin-inline-end"],ValueType::Length(&["auto"]),1),
+    (&["padding"],ValueType::Length(&[]),4),
+    (&["padding-block","padding-inline"],ValueType::Length(&[]),2),
+    (&["padding-top","padding-right","padding-bottom","padding-left","padding-block-start","padding-block-end","padding-inline-start","padding-inline-end"],ValueType::Length(&[]),1),
+    (&["gap"],ValueType::Length(&["normal"]),2),
+    (&["row-gap","column-gap"],ValueType::Length(&["normal"]),1),
+    (&["border-width"],ValueType::Length(BORDER_WIDTH_KEYWORDS),4),
+    (&["border-top-width","border-right-width","border-bottom-width","border-left-width","outline-width"],ValueType::Length(BORDER_WIDTH_KEYWORDS),1),
+    (&["border-radius"],ValueType::Length(&["/"]),9),
+    (&["border-top-left-radius","border-top-right-radius","border-bottom-left-radius","border-bottom-right-radius"],ValueType::Length(&[]),2),
+    (&["font-size"],ValueType::Length(FONT_SIZE_KEYWORDS),1),
+    (&["letter-spacing","word-spacing"],ValueType::Length(&["normal"]),1),
+    (&["text-indent"],ValueType::Length(&["hanging","each-line"]),3),
+    (&["outline-offset"],ValueType::Length(&[]),1),
+    (&["flex-basis"],ValueType::Length(&["auto","content","min-content","max-content","fit-content"]),1),
+    (&["color","background-color","text-decoration-color","column-rule-color"],ValueType::Color,1),
+    (&["border-top-color","border-right-color","border-bottom-color","border-left-color"],ValueType::Color,1),
+    (&["border-color"],ValueType::Color,4),
+    (&["z-index"],ValueType::Integer(&["auto"]),1),
+    (&["order"],ValueType::Integer(&[]),1),
+    (&["column-count"],ValueType::Integer(&["auto"]),1),
+    (&["opacity","fill-opacity","stroke-opacity","flex-grow","flex-shrink"],ValueType::Number(&[]),1),
+    (&["font-weight"],ValueType::Number(&["normal","bold","bolder","lighter"]),1),
+    (&["display"],ValueType::Keyword(DISPLAY_KEYWORDS),3),
+    (&["position"],ValueType::Keyword(&["static","relative","absolute","fixed","sticky"]),1),
+    (&["visibility"],ValueType::Keyword(&["visible","hidden","collapse"]),1),
+    (&["float"],ValueType::Keyword(&["left","right","none","inline-start","inline-end"]),1),
+    (&["clear"],ValueType::Keyword(&[
+        "left","right","top","bottom","both","both-inline","both-block","none","inline-start","inline-end","block-start",
+        "block-end",
+    ]),1),
+    (&["box-sizing"],ValueType::Keyword(&["content-box","border-box"]),1),
+    (&["overflow"],ValueType::Keyword(OVERFLOW_KEYWORDS),2),
+    (&["overflow-x","overflow-y","overflow-block","overflow-inline"],ValueType::Keyword(OVERFLOW_KEYWORDS),1),
+    (&["text-transform"],ValueType::Keyword(&["none","capitalize","uppercase","lowercase","full-width","full-size-kana","math-auto"]),3),
+    (&["flex-direction"],ValueType::Keyword(&["row","row-reverse","column","column-reverse"]),1),
+    (&["flex-wrap"],ValueType::Keyword(&["nowrap","wrap","wrap-reverse"]),1),
+    (&["white-space"],ValueType::Keyword(&[
+        "normal","pre","pre-wrap","pre-line","collapse","discard","preserve","preserve-breaks","preserve-spaces",
+        "break-spaces","wrap","nowrap","none","discard-before","discard-after","discard-inner",
+    ]),5),
+    (&["border-style","outline-style"],ValueType::Keyword(&["none","hidden","dotted","dashed","solid","double","groove","ridge","inset","outset","auto"]),4),
+    (&["box-decoration-break"],ValueType::Keyword(&["slice","clone"]),1),
+];
+#[derive(Debug,Clone,Copy)]
+enum ValueType {
+    Length(&'static [&'static str]),
+    Color,
+    Keyword(&'static [&'static str]),
+    Integer(&'static [&'static str]),
+    Number(&'static [&'static str]),
+}
+impl ValueType {
+    fn of(property:&str)->Option<(Self,usize)> {
+        VALUE_TYPES.iter()
+            .find(|(names,..)|names.iter().any(|n|n.eq_ignore_ascii_case(property)))
+            .map(|(_,ty,count)|(*ty,*count))
+    }
+    fn accepts(self,value:&AttributeData)->bool {
+        if let AttributeData::Text(text)=value {
+            if is_known(text,GLOBAL_KEYWORDS) {
+                return true;
+            }
+        }
+        match value {
+            AttributeData::Constant(..)|AttributeData::Var{..}|AttributeData::Raw(_)=>return true,
+            AttributeData::Function{name,..} if ["var","env","attr"].iter().any(|n|n.eq_ignore_ascii_case(name))=>return true,
+            AttributeData::Function{name,..} if MATH_FUNCTIONS.iter().any(|n|n.eq_ignore_ascii_case(name))=>{
+                return !matches!(self,Self::Color|Self::Keyword(_));
+            },
+            // functions we don't know anything about
+            AttributeData::Function{name,..} if !is_css_function(name)&&!is_color_function(name)=>return true,
+            AttributeData::Math{..}|AttributeData::Number{..}=>{
+                let kind=value.unit_kind().ok().flatten();
+                return match self {
+                    // `0` doesn't need a unit
+                    Self::Length(_) if value.folded_number().is_some_and(|(n,unit)|n==0.0&&unit.is_empty())=>true,
+                    Self::Length(_)=>matches!(kind,None|Some(UnitKind::Length|UnitKind::Percent)),
+                    Self::Integer(_)|Self::Number(_)=>matches!(kind,None|Some(UnitKind::Number|UnitKind::Percent)),
+                    Self::Color|Self::Keyword(_)=>false,
+                };
+            },
+            _=>{},
+        }
+        match self {
+            Self::Length(keywords)=>match value {
+                AttributeData::Text(text)=>match split_number(text) {
+                    Some((number,""))=>number.parse::<f64>().map(|n|n==0.0).unwrap_or(false),
+                    Some((_,unit))=>matches!(UnitKind::of(unit),UnitKind::Length|UnitKind::Percent),
+                    None=>is_known(text,keywords),
+                },
+                AttributeData::Function{name,..}=>name.eq_ignore_ascii_case("fit-content"),
+                _=>false,
+            },
+            Self::Color=>match value {
+                AttributeData::Text(text)=>{
+                    Color::from_value(value).is_some()||is_known(text,SYSTEM_COLORS)
+                },
+                AttributeData::Function{name,..}=>is_color_function(name),
+                AttributeData::Color(_)|AttributeData::ColorHelper{..}=>true,
+                _=>false,
+            },
+            Self::Keyword(keywords)=>matches!(value,AttributeData::Text(text) if is_known(text,keywords)),
+            Self::Integer(keywords)=>match value {
+                AttributeData::Text(text)=>text.parse::<i64>().is_ok()||is_known(text,keywords),
+                _=>false,
+            },
+            Self::Number(keywords)=>match value {
+                AttributeData::Text(text)=>match split_number(text) {
+                    Some((_,unit))=>unit.is_empty()||unit=="%",
+                    None=>is_known(text,keywords),
+                },
+                _=>false,
+            },
+        }
+    }
+}
+const NAMED_COLORS:&[(&str,u32)]=&[
+    ("aliceblue",0xf0f8ff),("antiquewhite",0xfaebd7),("aqua",0x00ffff),("aquamarine",0x7fffd4),("azure",0xf0ffff),
+    ("beige",0xf5f5dc),("bisque",0xffe4c4),("black",0x000000),("blanchedalmond",0xffebcd),("blue",0x0000ff),
+    ("blueviolet",0x8a2be2),("brown",0xa52a2a),("burlywood",0xdeb887),("cadetblue",0x5f9ea0),("chartreuse",0x7fff00),
+    ("chocolate",0xd2691e),("coral",0xff7f50),("cornflowerblue",0x6495ed),("cornsilk",0xfff8dc),("crimson",0xdc143c),
+    ("cyan",0x00ffff),("darkblue",0x00008b),("darkcyan",0x008b8b),("darkgoldenrod",0xb8860b),("darkgray",0xa9a9a9),
+    ("darkgreen",0x006400),("darkgrey",0xa9a9a9),("darkkhaki",0xbdb76b),("darkmagenta",0x8b008b),
+    ("darkolivegreen",0x556b2f),("darkorange",0xff8c00),("darkorchid",0x9932cc),("darkred",0x8b0000),
+    ("darksalmon",0xe9967a),("darkseagreen",0x8fbc8f),("darkslateblue",0x483d8b),("darkslategray",0x2f4f4f),
+    ("darkslategrey",0x2f4f4f),("darkturquoise",0x00ced1),("darkviolet",0x9400d3),("deeppink",0xff1493),
+    ("deepskyblue",0x00bfff),("dimgray",0x696969),("dimgrey",0x696969),("dodgerblue",0x1e90ff),("firebrick",0xb22222),
+    ("floralwhite",0xfffaf0),("forestgreen",0x228b22),("fuchsia",0xff00ff),("gainsboro",0xdcdcdc),
+    ("ghostwhite",0xf8f8ff),("gold",0xffd700),("goldenrod",0xdaa520),("gray",0x808080),("green",0x008000),
+    ("greenyellow",0xadff2f),("grey",0x808080),("honeydew",0xf0fff0),("hotpink",0xff69b4),("indianred",0xcd5c5c),
+    ("indigo",0x4b0082),("ivory",0xfffff0),("khaki",0xf0e68c),("lavender",0xe6e6fa),("lavenderblush",0xfff0f5),
+    ("lawngreen",0x7cfc00),("lemonchiffon",0xfffacd),("lightblue",0xadd8e6),("lightcoral",0xf08080),
+    ("lightcyan",0xe0ffff),("lightgoldenrodyellow",0xfafad2),("lightgray",0xd3d3d3),("lightgreen",0x90ee90),
+    ("lightgrey",0xd3d3d3),("lightpink",0xffb6c1),("lightsalmon",0xffa07a),("lightseagreen",0x20b2aa),
+    ("lightskyblue",0x87cefa),("lightslategray",0x778899),("lightslategrey",0x778899),("lightsteelblue",0xb0c4de),
+    ("lightyellow",0xffffe0),("lime",0x00ff00),("limegreen",0x32cd32),("linen",0xfaf0e6),("magenta",0xff00ff),
+    ("maroon",0x800000),("mediumaquamarine",0x66cdaa),("mediumblue",0x0000cd),("mediumorchid",0xba55d3),
+    ("mediumpurple",0x9370db),("mediumseagreen",0x3cb371),("mediumslateblue",0x7b68ee),
+    ("mediumspringgreen",0x00fa9a),("mediumturquoise",0x48d1cc),("mediumvioletred",0xc71585),
+    ("midnightblue",0x191970),("mintcream",0xf5fffa),("mistyrose",0xffe4e1),("moccasin",0xffe4b5),
+    ("navajowhite",0xffdead),("navy",0x000080),("oldlace",0xfdf5e6),("olive",0x808000),("olivedrab",0x6b8e23),
+    ("orange",0xffa500),("orangered",0xff4500),("orchid",0xda70d6),("palegoldenrod",0xeee8aa),("palegreen",0x98fb98),
+    ("paleturquoise",0xafeeee),("palevioletred",0xdb7093),("papayawhip",0xffefd5),("peachpuff",0xffdab9),
+    ("peru",0xcd853f),("pink",0xffc0cb),("plum",0xdda0dd),("powderblue",0xb0e0e6),("purple",0x800080),
+    ("rebeccapurple",0x663399),("red",0xff0000),("rosybrown",0xbc8f8f),("royalblue",0x4169e1),
+    ("saddlebrown",0x8b4513),("salmon",0xfa8072),("sandybrown",0xf4a460),("seagreen",0x2e8b57),("seashell",0xfff5ee),
+    ("sienna",0xa0522d),("silver",0xc0c0c0),("skyblue",0x87ceeb),("slateblue",0x6a5acd),("slategray",0x708090),
+    ("slategrey",0x708090),("snow",0xfffafa),("springgreen",0x00ff7f),("steelblue",0x4682b4),("tan",0xd2b48c),
+    ("teal",0x008080),("thistle",0xd8bfd8),("tomato",0xff6347),("turquoise",0x40e0d0),("violet",0xee82ee),
+    ("wheat",0xf5deb3),("white",0xffffff),("whitesmoke",0xf5f5f5),("yellow",0xffff00),("yellowgreen",0x9acd32),
+];
+const COLOR_HELPERS:&[&str]=&["lighten","darken","mix","alpha"];
+
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Color {
+    pub red:f64,
+    pub green:f64,
+    pub blue:f64,
+    pub alpha:f64,
+}
+impl Color {
+    pub fn new(red:f64,green:f64,blue:f64,alpha:f64)->Self {
+        Color{red,green,blue,alpha}
+    }
+    pub fn from_hex(text:&str)->Option<Self> {
+        let hex=text.strip_prefix('#')?;
+        if !hex.chars().all(|c|c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit=|i:usize|u8::from_str_radix(&hex[i..i+1],16).unwrap() as f64;
+        let pair=|i:usize|u8::from_str_radix(&hex[i..i+2],16).unwrap() as f64;
+        match hex.len() {
+            3=>Some(Self::new(digit(0)*17.0,digit(1)*17.0,digit(2)*17.0,1.0)),
+            4=>Some(Self::new(digit(0)*17.0,digit(1)*17.0,digit(2)*17.0,digit(3)*17.0/255.0)),
+            6=>Some(Self::new(pair(0),pair(2),pair(4),1.0)),
+            8=>Some(Self::new(pair(0),pair(2),pair(4),pair(6)/255.0)),
+            _=>None,
+        }
+    }
+    pub fn from_name(name:&str)->Option<Self> {
+        if name.eq_ignore_ascii_case("transparent") {
+            return Some(Self::new(0.0,0.0,0.0,0.0));
+        }
+        NAMED_COLORS.iter()
+            .find(|(n,_)|n.eq_ignore_ascii_case(name))
+            .map(|(_,rgb)|Self::new((rgb>>16) as f64,((rgb>>8)&0xff) as f64,(rgb&0xff) as f64,1.0))
+    }
+    pub fn from_value(value:&AttributeData)->Option<Self> {
+        match value {
+            AttributeData::Text(text) if text.starts_with('#')=>Self::from_hex(text),
+            AttributeData::Text(text)=>Self::from_name(text),
+            AttributeData::Color(color)=>Some(*color),
+            AttributeData::Function{name,args}=>Self::from_function(name,args),
+            _=>None,
+        }
+    }
+    fn from_function(name:&str,args:&[AttributeData])->Option<Self> {
+        let args=match args {
+            [AttributeData::List(items)]=>items.as_slice(),
+            _=>args,
+        };
+        let args:Vec<_>=args.iter().filter(|i|!matches!(i,AttributeData::Text("/"))).collect();
+        let (a,b,c,alpha)=match args.as_slice() {
+            [a,b,c]=>(a.as_number()?,b.as_number()?,c.as_number()?,1.0),
+            [a,b,c,alpha]=>{
+                let (alpha,unit)=alpha.as_number()?;
+                let alpha=if unit=="%" {alpha/100.0} else {alpha};
+                (a.as_number()?,b.as_number()?,c.as_number()?,alpha)
+            },
+            _=>return None,
+        };
+        // percentages are relative to `full`
+        let channel=|(value,unit):(f64,&str),full:f64|if unit=="%" {value*full/100.0} else {value};
+        let hue=|(value,unit):(f64,&str)|match unit.to_ascii_lowercase().as_str() {
+            ""|"deg"=>Some(value),
+            "rad"=>Some(value.to_degrees()),
+            "grad"=>Some(value*0.9),
+            "turn"=>Some(value*360.0),
+            _=>None,
+        };
+        let color=match name.to_ascii_lowercase().as_str() {
+            "rgb"|"rgba"=>Self::new(channel(a,255.0),channel(b,255.0),channel(c,255.0),alpha),
+            "hsl"|"hsla"=>Self::from_hsl(hue(a)?,channel(b,100.0)/100.0,channel(c,100.0)/100.0,alpha),
+            "hwb"=>Self::from_hwb(hue(a)?,channel(b,100.0)/100.0,channel(c,100.0)/100.0,alpha),
+            "lab"=>Self::from_lab(channel(a,100.0),channel(b,125.0),channel(c,125.0),alpha),
+            "lch"=>{
+                let (l,chroma,h)=(channel(a,100.0),channel(b,150.0),hue(c)?.to_radians());
+                Self::from_lab(l,chroma*h.cos(),chroma*h.sin(),alpha)
+            },
+            "oklab"=>Self::from_oklab(channel(a,1.0),channel(b,0.4),channel(c,0.4),alpha),
+            "oklch"=>{
+                let (l,chroma,h)=(channel(a,1.0),channel(b,0.4),hue(c)?.to_radians());
+                Self::from_oklab(l,chroma*h.cos(),chroma*h.sin(),alpha)
+            },
+            _=>return None,
+        };
+        Some(color)
+    }
+    pub fn from_hsl(hue:f64,saturation:f64,lightness:f64,alpha:f64)->Self {
+        let saturation=saturation.clamp(0.0,1.0);
+        let lightness=lightness.clamp(0.0,1.0);
+        let f=|n:f64|{
+            let k=(n+hue/30.0).rem_euclid(12.0);
+            let a=saturation*lightness.min(1.0-lightness);
+            (lightness-a*(k-3.0).min(9.0-k).clamp(-1.0,1.0))*255.0
+        };
+        Self::new(f(0.0),f(8.0),f(4.0),alpha)
+    }
+    fn from_hwb(hue:f64,white:f64,black:f64,alpha:f64)->Self {
+        if white+black>=1.0 {
+            let gray=white/(white+black)*255.0;
+            return Self::new(gray,gray,gray,alpha);
+        }
+        let pure=Self::from_hsl(hue,1.0,0.5,alpha);
+        let mix=|c:f64|c*(1.0-white-black)+white*255.0;
+        Self::new(mix(pure.red),mix(pure.green),mix(pure.blue),alpha)
+    }
+    fn from_lab(l:f64,a:f64,b:f64,alpha:f64)->Self {
+        const KAPPA:f64=24389.0/27.0;
+        const EPSILON:f64=216.0/24389.0;
+        let f1=(l+16.0)/116.0;
+        let f0=f1+a/500.0;
+        let f2=f1-b/200.0;
+        let x=if f0.powi(3)>EPSILON {f0.powi(3)} else {(116.0*f0-16.0)/KAPPA};
+        let y=if l>KAPPA*EPSILON {f1.powi(3)} else {l/KAPPA};
+        let z=if f2.powi(3)>EPSILON {f2.powi(3)} else {(116.0*f2-16.0)/KAPPA};
+        let (x,y,z)=(x*0.3457/0.3585,y,z*(1.0-0.3457-0.3585)/0.3585);
+        // Bradford adaptation from D50 to D65
+        let (x,y,z)=(
+            0.955_473_452_704_218_2*x-0.023_098_536_874_261_423*y+0.063_259_308_661_021_7*z,
+            -0.028_369_706_963_208_136*x+1.009_995_458_005_822_6*y+0.021_041_398_966_943_008*z,
+            0.012_314_001_688_319_899*x-0.020_507_696_433_477_912*y+1.330_365_936_608_075_3*z,
+        );
+        Self::from_linear_srgb(
+            3.240_969_941_904_522_6*x-1.537_383_177_570_094*y-0.498_610_760_293_003_4*z,
+            -0.969_243_636_280_879_6*x+1.875_967_501_507_720_2*y+0.041_555_057_407_175_59*z,
+            0.055_630_079_696_993_66*x-0.203_976_958_888_976_52*y+1.056_971_514_242_878_6*z,
+            alpha,
+        )
+    }
+    fn from_oklab(l:f64,a:f64,b:f64,alpha:f64)->Self {
+        let l_=(l+0.396_337_777_4*a+0.215_803_757_3*b).powi(3);
+        let m_=(l-0.105_561_345_8*a-0.063_854_172_8*b).powi(3);
+        let s_=(l-0.089_484_177_5*a-1.291_485_548*b).powi(3);
+        Self::from_linear_srgb(
+            4.076_741_662_1*l_-3.307_711_591_3*m_+0.230_969_929_2*s_,
+            -1.268_438_004_6*l_+2.609_757_401_1*m_-0.341_319_396_5*s_,
+            -0.004_196_086_3*l_-0.703_418_614_7*m_+1.707_614_701*s_,
+            alpha,
+        )
+    }
+    fn from_linear_srgb(red:f64,green:f64,blue:f64,alpha:f64)->Self {
+        let gamma=|c:f64|{
+            let c=c.clamp(0.0,1.0);
+            let c=if c<=0.003_130_8 {c*12.92} else {1.055*c.powf(1.0/2.4)-0.055};
+            c*255.0
+        };
+        Self::new(gamma(red),gamma(green),gamma(blue),alpha)
+    }
+    pub fn to_hsl(self)->(f64,f64,f64) {
+        let (r,g,b)=(self.red/255.0,self.green/255.0,self.blue/255.0);
+        let max=r.max(g).max(b);
+        let min=r.min(g).min(b);
+        let lightness=(max+min)/2.0;
+        let delta=max-min;
+        if delta==0.0 {
+            return (0.0,0.0,lightness);
+        }
+        let saturation=delta/(1.0-(2.0*lightness-1.0).abs());
+        let hue=if max==r {
+            ((g-b)/delta).rem_euclid(6.0)
+        } else if max==g {
+            (b-r)/delta+2.0
+        } else {
+            (r-g)/delta+4.0
+        };
+        (hue*60.0,saturation,lightness)
+    }
+    pub fn lighten(self,amount:f64)->Self {
+        let (hue,saturation,lightness)=self.to_hsl();
+        Self::from_hsl(hue,saturation,lightness+amount,self.alpha)
+    }
+    pub fn darken(self,amount:f64)->Self {
+        self.lighten(-amount)
+    }
+    pub fn mix(self,other:Self,weight:f64)->Self {
+        let weight=weight.clamp(0.0,1.0);
+        let mix=|a:f64,b:f64|a*weight+b*(1.0-weight);
+        Self::new(
+            mix(self.red,other.red),
+            mix(self.green,other.green),
+            mix(self.blue,other.blue),
+            mix(self.alpha,other.alpha),
+        )
+    }
+    pub fn with_alpha(self,alpha:f64)->Self {
+        Self{alpha:alpha.clamp(0.0,1.0),..self}
+    }
+    pub fn into_css<W:Write>(&self,f:&mut W)->FmtResult {
+        let byte=|c:f64|c.round().clamp(0.0,255.0) as u8;
+        write!(f,"#{:02x}{:02x}{:02x}",byte(self.red),byte(self.green),byte(self.blue))?;
+        if self.alpha<1.0 {
+            write!(f,"{:02x}",byte(self.alpha*255.0))?;
+        }
+        Ok(())
+    }
+}
+
+
+fn is_css_function(name:&str)->bool {
+    let name=["-webkit-","-moz-","-ms-","-o-"].iter()
+        .find_map(|prefix|name.strip_prefix(prefix))
+        .unwrap_or(name);
+    CSS_FUNCTIONS.iter().any(|f|f.eq_ignore_ascii_case(name))
+}
+fn suggest(name:&str,known:&[&'static str])->Option<&'static str> {
+    let max=if name.len()<=4 {1} else {2};
+    known.iter()
+        .map(|k|(edit_distance(&name.to_ascii_lowercase(),k),*k))
+        .filter(|(distance,_)|*distance>0&&*distance<=max)
+        .min_by_key(|(distance,_)|*distance)
+        .map(|(_,k)|k)
+}
+fn edit_distance(a:&str,b:&str)->usize {
+    let a=a.as_bytes();
+    let b=b.as_bytes();
+    let mut rows=vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row=vec![i;b.len()+1];
+        for j in 1..=b.len() {
+            let cost=if a[i-1]==b[j-1] {0} else {1};
+            row[j]=(rows[i-1][j]+1).min(row[j-1]+1).min(rows[i-1][j-1]+cost);
+            if i>1&&j>1&&a[i-1]==b[j-2]&&a[i-2]==b[j-1] {
+                row[j]=row[j].min(rows[i-2][j-2]+1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+fn for_each_declaration<'a,'input,F:FnMut(&'input str,&'a AttributeData<'input>)>(items:&'a [Item<'input>],found:&mut F) {
+    let declarations=|attributes:&'a [(&'input str,AttributeData<'input>)],found:&mut F|{
+        attributes.iter().for_each(|(name,data)|found(name,data));
+    };
+    for item in items {
+        if let Some(inner)=item.block() {
+            for_each_declaration(inner,found);
+        }
+        match item {
+            Item::Rule(rule)=>{
+                declarations(&rule.inner,found);
+                for_each_declaration(&rule.nested,found);
+            },
+            Item::Keyframes{rules,..}=>{
+                for rule in rules {
+                    declarations(&rule.attributes,found);
+                }
+            },
+            _=>{},
+        }
+    }
+}
+fn is_color_function(name:&str)->bool {
+    ["rgb","rgba","hsl","hsla","hwb","lab","lch","oklab","oklch","color","color-mix","light-dark"].iter().any(|n|n.eq_ignore_ascii_case(name))
+}
+fn is_keyword(name:&str)->bool {
+    [GLOBAL_KEYWORDS,SIZE_KEYWORDS,FONT_SIZE_KEYWORDS,SYSTEM_COLORS,BORDER_WIDTH_KEYWORDS,OVERFLOW_KEYWORDS,DISPLAY_KEYWORDS,COMMON_KEYWORDS]
+        .iter()
+        .any(|keywords|keywords.iter().any(|k|k.eq_ignore_ascii_case(name)))
+        ||NAMED_COLORS.iter().any(|(color,_)|color.eq_ignore_ascii_case(name))
+}
+fn is_known(name:&str,known:&[&str])->bool {
+    name.starts_with('-')||known.iter().any(|k|k.eq_ignore_ascii_case(name))
+}
+fn shorten_hex_color(text:&str)->Option<String> {
+    let hex=text.strip_prefix('#')?;
+    if !(hex.len()==6||hex.len()==8)||!hex.chars().all(|c|c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let pairs=hex.as_bytes().chunks(2);
+    if pairs.clone().all(|pair|pair[0].eq_ignore_ascii_case(&pair[1])) {
+        Some(pairs.fold(String::from("#"),|mut out,pair|{out.push(pair[0].to_ascii_lowercase() as char);out}))
+    } else {
+        None
+    }
+}
+fn shorten_number(text:&str,keep_units:bool)->Option<String> {
+    let (number,unit)=split_number(text)?;
+    if !keep_units&&number.parse::<f64>().ok()?==0.0&&LENGTH_UNITS.iter().any(|u|u.eq_ignore_ascii_case(unit)) {
+        return Some(String::from("0"));
+    }
+    if let Some(rest)=number.strip_prefix("0.") {
+        Some(format!(".{}{}",rest,unit))
+    } else {
+        number.strip_prefix("-0.").map(|rest|format!("-.{}{}",rest,unit))
+    }
+}
+fn write_declarations<W:Write>(f:&mut W,attributes:&[(&str,AttributeData)],options:&RenderOptions,depth:usize)->FmtResult {
+    if options.compact_rules {
+        f.write_str(" {")?;
+        for (name,data) in attributes {
+            write!(f," {}: ",name)?;
+            data.into_css(f)?;
+            f.write_char(';')?;
+        }
+        writeln!(f," }}")
+    } else {
+        writeln!(f," {{")?;
+        for (name,data) in attributes {
+            options.write_indent(f,depth+1)?;
+            write!(f,"{}: ",name)?;
+            data.into_css(f)?;
+            writeln!(f,";")?;
+        }
+        options.write_indent(f,depth)?;
+        writeln!(f,"}}")
+    }
+}
+fn write_min_declarations<W:Write>(f:&mut W,attributes:&[(&str,AttributeData)])->FmtResult {
+    for (i,(name,data)) in attributes.iter().enumerate() {
+        if i!=0 {
+            f.write_char(';')?;
+        }
+        write!(f,"{}:",name)?;
+        if name.starts_with("--") {
+            // custom properties get substituted into other values, so `0px` has to stay `0px`
+            data.into_css(f)?;
+        } else {
+            // a unitless `0` in `flex` is the shrink factor instead of the basis
+            let keep_units=KEEP_ZERO_UNITS.iter().any(|p|p.eq_ignore_ascii_case(name.trim_start_matches("-webkit-")));
+            data.into_min_css(f,keep_units)?;
+        }
+    }
+    Ok(())
+}
+
+
+#[derive(Debug,Clone)]
 pub enum AttributeData<'input> {
     NotImportant(Box<Self>),
     Function {
@@ -31,25 +796,409 @@ pub enum AttributeData<'input> {
         args:Vec<Self>,
     },
     List(Vec<Self>),
+    Var {
+        name:&'input str,
+        fallback:Option<Box<Self>>,
+    },
     Text(&'input str),
     String(&'input str),
+    Raw(&'input str),
+    Constant(&'input str,Location,Location),
+    Math {
+        operator:&'input str,
+        args:Vec<Self>,
+        start:Location,
+        end:Location,
+    },
+    Number {
+        value:f64,
+        unit:&'input str,
+    },
+    ColorHelper {
+        name:&'input str,
+        args:Vec<Self>,
+        start:Location,
+        end:Location,
+    },
+    Color(Color),
 }
 impl<'input> AttributeData<'input> {
+    pub fn declaration(property:&str,raw:&'input Object<'input>)->Result<Self,Error> {
+        let value=Self::try_from(raw)?;
+        let (Object::List(start,_,end)|Object::Ident(start,_,end)|Object::Number(start,_,end)|Object::String(start,_,end))=raw;
+        value.check(property,*start,*end)?;
+        Ok(value)
+    }
+    fn check(&self,property:&str,start:Location,end:Location)->Result<(),Error> {
+        if let Some((ty,count))=ValueType::of(property) {
+            let fits=match self {
+                Self::NotImportant(inner)=>Self::fits(ty,count,inner),
+                _=>Self::fits(ty,count,self),
+            };
+            if !fits {
+                return Err(Error{start,end,err_type:ErrorType::InvalidValue});
+            }
+        }
+        Ok(())
+    }
+    pub fn resolve_declaration(&mut self,property:&str,constants:&Constants<'input>)->Result<(),Error> {
+        let constant=self.constant_location();
+        self.resolve(constants)?;
+        if let Some((start,end))=constant {
+            self.check(property,start,end)?;
+        }
+        Ok(())
+    }
+    fn constant_location(&self)->Option<(Location,Location)> {
+        match self {
+            Self::Constant(_,start,end)=>Some((*start,*end)),
+            Self::List(items)|Self::Function{args:items,..}|Self::Math{args:items,..}|Self::ColorHelper{args:items,..}=>{
+                items.iter().find_map(Self::constant_location)
+            },
+            Self::NotImportant(item)=>item.constant_location(),
+            Self::Var{fallback:Some(fallback),..}=>fallback.constant_location(),
+            _=>None,
+        }
+    }
+    fn fits(ty:ValueType,count:usize,value:&Self)->bool {
+        match value {
+            Self::List(items)=>items.len()<=count&&items.iter().all(|i|ty.accepts(i)),
+            _=>ty.accepts(value),
+        }
+    }
+    fn function(name:&'input str,raw_args:&'input [Object<'input>])->Result<Self,Error> {
+        let mut args=Vec::new();
+        for a in raw_args {
+            match a {
+                // `url(#id)` isn't a color
+                Object::Ident(_,text,_) if name.eq_ignore_ascii_case("url")=>args.push(Self::Text(text)),
+                _=>args.push(a.try_into()?),
+            }
+        }
+        Ok(Self::Function{name,args})
+    }
+    fn is_space_separated(name:&str,args:&[Self])->bool {
+        SPACE_SEPARATED_FUNCTIONS.iter().any(|n|n.eq_ignore_ascii_case(name))||args.iter().any(|a|matches!(a,Self::Text("/")))
+    }
+    pub fn custom_value(raw:&'input [Object<'input>])->Result<Self,Error> {
+        match raw {
+            [single]=>single.try_into(),
+            _=>{
+                let mut items=Vec::new();
+                for i in raw {
+                    items.push(i.try_into()?);
+                }
+                Ok(Self::List(items))
+            },
+        }
+    }
+    fn as_number(&self)->Option<(f64,&'input str)> {
+        match self {
+            Self::Text(text)=>{
+                let (number,unit)=split_number(text)?;
+                Some((number.parse().ok()?,unit))
+            },
+            Self::Number{value,unit}=>Some((*value,unit)),
+            _=>None,
+        }
+    }
+    fn folded_number(&self)->Option<(f64,&'input str)> {
+        match self {
+            Self::Math{operator,args,..} if !args.is_empty()=>Self::fold_math(operator,args),
+            _=>self.as_number(),
+        }
+    }
+    fn unit_kind(&self)->Result<Option<UnitKind>,Error> {
+        match self {
+            Self::Math{operator,args,start,end}=>Self::math_kind(operator,args,*start,*end),
+            _=>Ok(self.as_number().map(|(_,unit)|UnitKind::of(unit))),
+        }
+    }
+    fn math_kind(operator:&str,args:&[Self],start:Location,end:Location)->Result<Option<UnitKind>,Error> {
+        let err=|err_type|Error{start,end,err_type};
+        let mut kinds=Vec::new();
+        for arg in args {
+            kinds.push(arg.unit_kind()?);
+        }
+        match operator {
+            "+"|"-"=>{
+                let mut result=None;
+                for kind in kinds.into_iter().flatten() {
+                    result=match result {
+                        None=>Some(kind),
+                        Some(result)=>Some(UnitKind::add(result,kind).ok_or_else(||err(ErrorType::IncompatibleUnits))?),
+                    };
+                }
+                Ok(result)
+            },
+            "*"=>{
+                let mut dimensions=kinds.iter().flatten().filter(|k|**k!=UnitKind::Number);
+                let dimension=dimensions.next().copied();
+                if dimensions.next().is_some() {
+                    return Err(err(ErrorType::IncompatibleUnits));
+                }
+                if dimension.is_none()&&kinds.iter().all(Option::is_some) {
+                    Ok(Some(UnitKind::Number))
+                } else {
+                    Ok(dimension)
+                }
+            },
+            _=>{
+                for (arg,kind) in args.iter().zip(kinds.iter()).skip(1) {
+                    match kind {
+                        Some(UnitKind::Number) if arg.as_number().map(|(value,_)|value==0.0).unwrap_or(false)=>{
+                            return Err(err(ErrorType::DivisionByZero));
+                        },
+                        Some(UnitKind::Number)|None=>{},
+                        Some(_)=>return Err(err(ErrorType::IncompatibleUnits)),
+                    }
+                }
+                Ok(kinds[0])
+            },
+        }
+    }
+    fn fold_math(operator:&str,args:&[Self])->Option<(f64,&'input str)> {
+        let mut numbers=Vec::new();
+        for arg in args {
+            numbers.push(arg.as_number()?);
+        }
+        let (first,mut unit)=numbers[0];
+        let rest=&numbers[1..];
+        let value=match operator {
+            "+"|"-"=>{
+                if rest.iter().any(|(_,other)|!other.eq_ignore_ascii_case(unit)) {
+                    return None;
+                }
+                if rest.is_empty()&&operator=="-" {
+                    -first
+                } else if operator=="+" {
+                    rest.iter().fold(first,|acc,(value,_)|acc+value)
+                } else {
+                    rest.iter().fold(first,|acc,(value,_)|acc-value)
+                }
+            },
+            "*"=>{
+                if let Some((_,other))=rest.iter().find(|(_,other)|!other.is_empty()) {
+                    unit=other;
+                }
+                rest.iter().fold(first,|acc,(value,_)|acc*value)
+            },
+            _=>rest.iter().fold(first,|acc,(value,_)|acc/value),
+        };
+        Some((value,unit))
+    }
+    fn write_number<W:Write>(value:f64,unit:&str,f:&mut W)->FmtResult {
+        let rounded=(value*10000.0).round()/10000.0;
+        // no `-0`
+        let rounded=if rounded==0.0 {0.0} else {rounded};
+        write!(f,"{}{}",rounded,unit)
+    }
+    fn write_math<W:Write>(operator:&str,args:&[Self],f:&mut W,minify:bool)->FmtResult {
+        if args.len()==1 {
+            // `(- x)` is `-1 * x`
+            if operator=="-" {
+                f.write_str(if minify {"-1*"} else {"-1 * "})?;
+            }
+        }
+        for (i,arg) in args.iter().enumerate() {
+            if i!=0 {
+                // `+` and `-` always need spaces around them
+                if minify&&(operator=="*"||operator=="/") {
+                    f.write_str(operator)?;
+                } else {
+                    write!(f," {} ",operator)?;
+                }
+            }
+            match arg {
+                Self::Math{operator,args,..}=>{
+                    f.write_char('(')?;
+                    Self::write_math(operator,args,f,minify)?;
+                    f.write_char(')')?;
+                },
+                _ if minify=>arg.into_min_css(f,true)?,
+                _=>arg.into_css(f)?,
+            }
+        }
+        Ok(())
+    }
+    fn write_var_name<W:Write>(name:&str,f:&mut W)->FmtResult {
+        if !name.starts_with("--") {
+            f.write_str("--")?;
+        }
+        f.write_str(name)
+    }
+    pub fn replace_values<F:Fn(&Self)->Option<Self>>(&self,replace:&F)->Option<Self> {
+        if let Some(replaced)=replace(self) {
+            return Some(replaced);
+        }
+        match self {
+            Self::List(items)=>Self::replace_all(items,replace).map(Self::List),
+            Self::Function{name,args}=>Self::replace_all(args,replace).map(|args|Self::Function{name,args}),
+            Self::NotImportant(item)=>item.replace_values(replace).map(|item|Self::NotImportant(Box::new(item))),
+            _=>None,
+        }
+    }
+    fn replace_all<F:Fn(&Self)->Option<Self>>(items:&[Self],replace:&F)->Option<Vec<Self>> {
+        let replaced=items.iter().map(|i|i.replace_values(replace)).collect::<Vec<_>>();
+        if replaced.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(replaced.into_iter().zip(items).map(|(r,i)|r.unwrap_or_else(||i.clone())).collect())
+    }
+    fn lab_as_srgb(&self)->Option<Self> {
+        match self {
+            Self::Function{name,..} if ["lab","lch","oklab","oklch"].iter().any(|n|n.eq_ignore_ascii_case(name))=>{
+                Color::from_value(self).map(Self::Color)
+            },
+            _=>None,
+        }
+    }
+    fn clamp_as_min_max(&self)->Option<Self> {
+        match self {
+            Self::Function{name,args} if name.eq_ignore_ascii_case("clamp")&&args.len()==3=>{
+                let upper=Self::Function{name:"min",args:vec![args[1].clone(),args[2].clone()]};
+                Some(Self::Function{name:"max",args:vec![args[0].clone(),upper]})
+            },
+            _=>None,
+        }
+    }
+    fn clamp_upper_bound(&self)->Option<Self> {
+        match self {
+            Self::Function{name,args} if name.eq_ignore_ascii_case("clamp")&&args.len()==3=>Some(args[2].clone()),
+            _=>None,
+        }
+    }
+    fn split_pair(&self)->(Self,Self) {
+        match self {
+            Self::List(items) if items.len()==2=>(items[0].clone(),items[1].clone()),
+            Self::NotImportant(item)=>{
+                let (first,second)=item.split_pair();
+                (Self::NotImportant(Box::new(first)),Self::NotImportant(Box::new(second)))
+            },
+            _=>(self.clone(),self.clone()),
+        }
+    }
+    pub fn for_each_list<'a,F:FnMut(&'a [Self])>(&'a self,found:&mut F) {
+        match self {
+            Self::List(items)=>{
+                found(items);
+                items.iter().for_each(|i|i.for_each_list(found));
+            },
+            Self::Function{args:items,..}|Self::Math{args:items,..}|Self::ColorHelper{args:items,..}=>{
+                items.iter().for_each(|i|i.for_each_list(found))
+            },
+            Self::NotImportant(item)=>item.for_each_list(found),
+            Self::Var{fallback:Some(fallback),..}=>fallback.for_each_list(found),
+            Self::Var{fallback:None,..}|Self::Text(_)|Self::String(_)|Self::Raw(_)|Self::Constant(..)|Self::Number{..}|Self::Color(_)=>{},
+        }
+    }
+    pub fn for_each_var<'a,F:FnMut(&'a str)>(&'a self,found:&mut F) {
+        match self {
+            Self::Var{name,fallback}=>{
+                found(name);
+                if let Some(fallback)=fallback {
+                    fallback.for_each_var(found);
+                }
+            },
+            Self::Function{name:"var",args}=>{
+                if let Some(Self::Text(name))=args.first() {
+                    found(name);
+                }
+                args.iter().skip(1).for_each(|a|a.for_each_var(found));
+            },
+            Self::Function{args:items,..}|Self::List(items)|Self::Math{args:items,..}|Self::ColorHelper{args:items,..}=>{
+                items.iter().for_each(|i|i.for_each_var(found))
+            },
+            Self::NotImportant(item)=>item.for_each_var(found),
+            Self::Text(_)|Self::String(_)|Self::Raw(_)|Self::Constant(..)|Self::Number{..}|Self::Color(_)=>{},
+        }
+    }
+    pub fn resolve(&mut self,constants:&Constants<'input>)->Result<(),Error> {
+        match self {
+            Self::Math{operator,args,start,end}=>{
+                for arg in args.iter_mut() {
+                    arg.resolve(constants)?;
+                }
+                Self::math_kind(operator,args,*start,*end)?;
+                if let Some((value,unit))=Self::fold_math(operator,args) {
+                    *self=Self::Number{value,unit};
+                }
+            },
+            Self::ColorHelper{name,args,start,end}=>{
+                for arg in args.iter_mut() {
+                    arg.resolve(constants)?;
+                }
+                *self=Self::Color(Self::color_helper(name,args,*start,*end)?);
+            },
+            Self::Constant(name,start,end)=>{
+                *self=constants.get(name).ok_or(Error{start:*start,end:*end,err_type:ErrorType::UndefinedConstant})?;
+            },
+            Self::NotImportant(item)=>item.resolve(constants)?,
+            Self::Function{args:items,..}|Self::List(items)=>{
+                for item in items.iter_mut() {
+                    item.resolve(constants)?;
+                }
+                // math is already written as `calc()`
+                if let Self::Function{name:"calc",args}=self {
+                    if let [math@Self::Math{..}]=args.as_slice() {
+                        *self=math.clone();
+                    }
+                }
+            },
+            Self::Var{fallback:Some(fallback),..}=>fallback.resolve(constants)?,
+            Self::Var{fallback:None,..}|Self::Text(_)|Self::String(_)|Self::Raw(_)|Self::Number{..}|Self::Color(_)=>{},
+        }
+        Ok(())
+    }
+    fn color_helper(name:&str,args:&[Self],start:Location,end:Location)->Result<Color,Error> {
+        let err=|err_type|Error{start,end,err_type};
+        let color=|arg:&Self|Color::from_value(arg).ok_or_else(||err(ErrorType::InvalidColor));
+        let amount=|arg:&Self|match arg.as_number() {
+            Some((value,"%"))=>Ok(value/100.0),
+            Some((value,""))=>Ok(value),
+            _=>Err(err(ErrorType::ExpectedPercent)),
+        };
+        match (name,args) {
+            ("lighten",[c,a])=>Ok(color(c)?.lighten(amount(a)?)),
+            ("darken",[c,a])=>Ok(color(c)?.darken(amount(a)?)),
+            ("alpha",[c,a])=>Ok(color(c)?.with_alpha(amount(a)?)),
+            ("mix",[a,b])=>Ok(color(a)?.mix(color(b)?,0.5)),
+            ("mix",[a,b,weight])=>Ok(color(a)?.mix(color(b)?,amount(weight)?)),
+            _=>Err(err(ErrorType::WrongArgumentCount)),
+        }
+    }
     pub fn into_css<W:Write>(&self,f:&mut W)->FmtResult {
         match self {
-            Self::Text(text)=>f.write_str(text),
+            Self::Text(text)|Self::Raw(text)=>f.write_str(text),
+            Self::Constant(name,..)=>f.write_str(name),
+            Self::Number{value,unit}=>Self::write_number(*value,unit,f),
+            Self::Color(color)=>color.into_css(f),
+            Self::ColorHelper{name,..}=>f.write_str(name),
+            Self::Math{operator,args,..}=>{
+                f.write_str("calc(")?;
+                Self::write_math(operator,args,f,false)?;
+                f.write_char(')')
+            },
+            Self::Var{name,fallback}=>{
+                f.write_str("var(")?;
+                Self::write_var_name(name,f)?;
+                if let Some(fallback)=fallback {
+                    f.write_str(", ")?;
+                    fallback.into_css(f)?;
+                }
+                f.write_char(')')
+            },
             Self::String(s)=>{
                 f.write_char('"')?;
                 f.write_str(s)?;
                 f.write_char('"')
             },
             Self::List(items)=>{
-                let last=items.len()-1;
                 for (i,item) in items.iter().enumerate() {
-                    item.into_css(f)?;
-                    if i!=last {
-                        write!(f," ")?;
+                    if i!=0 {
+                        f.write_char(' ')?;
                     }
+                    item.into_css(f)?;
                 }
                 Ok(())
             },
@@ -59,11 +1208,78 @@ impl<'input> AttributeData<'input> {
             },
             Self::Function{name,args}=>{
                 write!(f,"{}(",name)?;
-                let last=args.len()-1;
+                let separator=if Self::is_space_separated(name,args) {" "} else {", "};
                 for (i,item) in args.iter().enumerate() {
+                    if i!=0 {
+                        f.write_str(separator)?;
+                    }
                     item.into_css(f)?;
-                    if i!=last {
-                        write!(f,", ")?;
+                }
+                write!(f,")")
+            },
+        }
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W,keep_units:bool)->FmtResult {
+        match self {
+            Self::Text(text)=>{
+                if let Some(short)=shorten_hex_color(text).or_else(||shorten_number(text,keep_units)) {
+                    f.write_str(&short)
+                } else {
+                    f.write_str(text)
+                }
+            },
+            Self::String(_)|Self::Raw(_)|Self::Constant(..)|Self::ColorHelper{..}=>self.into_css(f),
+            Self::Color(color)=>{
+                let mut text=String::new();
+                color.into_css(&mut text)?;
+                f.write_str(&shorten_hex_color(&text).unwrap_or(text))
+            },
+            Self::Number{value,unit}=>{
+                let mut text=String::new();
+                Self::write_number(*value,unit,&mut text)?;
+                f.write_str(&shorten_number(&text,keep_units).unwrap_or(text))
+            },
+            Self::Math{operator,args,..}=>{
+                f.write_str("calc(")?;
+                Self::write_math(operator,args,f,true)?;
+                f.write_char(')')
+            },
+            Self::Var{name,fallback}=>{
+                f.write_str("var(")?;
+                Self::write_var_name(name,f)?;
+                if let Some(fallback)=fallback {
+                    f.write_char(',')?;
+                    fallback.into_min_css(f,keep_units)?;
+                }
+                f.write_char(')')
+            },
+            Self::List(items)=>{
+                for (i,item) in items.iter().enumerate() {
+                    if i!=0 {
+                        f.write_char(' ')?;
+                    }
+                    item.into_min_css(f,keep_units)?;
+                }
+                Ok(())
+            },
+            Self::NotImportant(item)=>{
+                item.into_min_css(f,keep_units)?;
+                write!(f,"!important")
+            },
+            Self::Function{name,args}=>{
+                let keep_units=keep_units||MATH_FUNCTIONS.iter().any(|n|n.eq_ignore_ascii_case(name));
+                let separator=if Self::is_space_separated(name,args) {' '} else {','};
+                // `url(#aabbcc)` points at an element id, so the arguments aren't colors
+                let verbatim=VERBATIM_FUNCTIONS.iter().any(|n|n.eq_ignore_ascii_case(name));
+                write!(f,"{}(",name)?;
+                for (i,item) in args.iter().enumerate() {
+                    if i!=0 {
+                        f.write_char(separator)?;
+                    }
+                    if verbatim {
+                        item.into_css(f)?;
+                    } else {
+                        item.into_min_css(f,keep_units)?;
                     }
                 }
                 write!(f,")")
@@ -75,16 +1291,39 @@ impl<'input> TryFrom<&'input Object<'input>> for AttributeData<'input> {
     type Error=Error;
     fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
         match o {
+            Object::Ident(s,data,e) if data.starts_with('$')=>Ok(Self::Constant(data,*s,*e)),
+            Object::Ident(s,data,e)|Object::Number(s,data,e) if data.starts_with('#')&&Color::from_hex(data).is_none()=>{
+                Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidColor})
+            },
             Object::Ident(_,data,_)|Object::Number(_,data,_)=>Ok(Self::Text(data)),
             Object::List(s,items,e)=>{
                 match items.as_slice() {
                     [Object::Ident(_,"!important",_),attr]=>Ok(Self::NotImportant(Box::new(attr.try_into()?))),
-                    [Object::Ident(_,"fn",_),Object::Ident(_,name,_),raw_args@..]=>{
+                    [Object::Ident(_,"fn",_),Object::Ident(_,name,_),raw_args@..]=>Self::function(name,raw_args),
+                    [Object::Ident(_,name,_),raw_args@..] if COLOR_HELPERS.contains(name)=>{
                         let mut args=Vec::new();
                         for a in raw_args {
                             args.push(a.try_into()?);
                         }
-                        Ok(Self::Function{name,args})
+                        Ok(Self::ColorHelper{name,args,start:*s,end:*e})
+                    },
+                    [Object::Ident(_,"var",_),Object::Ident(_,name,_),raw_fallback@..]=>{
+                        let fallback=match raw_fallback {
+                            []=>None,
+                            _=>Some(Box::new(Self::custom_value(raw_fallback)?)),
+                        };
+                        Ok(Self::Var{name,fallback})
+                    },
+                    [Object::Ident(_,"raw",_),Object::String(_,text,_)]=>Ok(Self::Raw(text)),
+                    // `(fn name)` has to be used for a function without any arguments
+                    [Object::Ident(_,name,_)] if is_css_function(name)=>Err(Error{start:*s,end:*e,err_type:ErrorType::WrongArgumentCount}),
+                    [Object::Ident(_,name,_),raw_args@..] if is_css_function(name)=>Self::function(name,raw_args),
+                    [Object::Ident(_,operator@("+"|"-"|"*"|"/"),_),raw_args@..] if !raw_args.is_empty()=>{
+                        let mut args=Vec::new();
+                        for a in raw_args {
+                            args.push(a.try_into()?);
+                        }
+                        Ok(Self::Math{operator,args,start:*s,end:*e})
                     },
                     []=>Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
                     _=>{
@@ -104,36 +1343,276 @@ impl<'input> TryFrom<&'input Object<'input>> for AttributeData<'input> {
 pub enum SelectorType<'input> {
     List(Vec<Self>),
     Sequence(Vec<Self>),
+    Child(Vec<Self>),
+    NextSibling(Vec<Self>),
+    Siblings(Vec<Self>),
+    Compound(Vec<Self>),
+    Pseudo(&'input str),
+    PseudoSelector(&'input str,Vec<Self>),
+    PseudoArgs(&'input str,Vec<&'input str>),
+    Attribute {
+        name:&'input str,
+        test:Option<(&'input str,&'input str)>,
+        flag:Option<&'input str>,
+    },
     Class(&'input str),
     Id(&'input str),
     Tag(&'input str),
+    Parent(&'input str),
+    Suffixed(Box<Self>,&'input str),
 }
 impl<'input> SelectorType<'input> {
+    fn join_into_css<W:Write>(items:&[Self],f:&mut W,separator:&str)->FmtResult {
+        for (i,item) in items.iter().enumerate() {
+            if i!=0 {
+                f.write_str(separator)?;
+            }
+            item.into_css(f)?;
+        }
+        Ok(())
+    }
+    fn join_into_min_css<W:Write>(items:&[Self],f:&mut W,separator:&str)->FmtResult {
+        for (i,item) in items.iter().enumerate() {
+            if i!=0 {
+                f.write_str(separator)?;
+            }
+            item.into_min_css(f)?;
+        }
+        Ok(())
+    }
     pub fn into_css<W:Write>(&self,f:&mut W)->FmtResult {
         match self {
-            Self::List(items)=>{
-                let last=items.len()-1;
-                for (i,item) in items.iter().enumerate() {
-                    item.into_css(f)?;
-                    if i!=last {
-                        write!(f,", ")?;
+            Self::List(items)=>Self::join_into_css(items,f,", "),
+            Self::Sequence(items)=>Self::join_into_css(items,f," "),
+            Self::Child(items)=>Self::join_into_css(items,f," > "),
+            Self::NextSibling(items)=>Self::join_into_css(items,f," + "),
+            Self::Siblings(items)=>Self::join_into_css(items,f," ~ "),
+            Self::Compound(items)=>Self::join_into_css(items,f,""),
+            Self::Pseudo(name)=>f.write_str(name),
+            Self::PseudoSelector(name,args)=>{
+                write!(f,"{}(",name)?;
+                Self::join_into_css(args,f,", ")?;
+                f.write_char(')')
+            },
+            Self::PseudoArgs(name,args)=>{
+                write!(f,"{}(",name)?;
+                for (i,arg) in args.iter().enumerate() {
+                    if i!=0 {
+                        f.write_char(' ')?;
                     }
+                    f.write_str(arg)?;
                 }
-                Ok(())
+                f.write_char(')')
             },
-            Self::Sequence(items)=>{
-                let last=items.len()-1;
-                for (i,item) in items.iter().enumerate() {
-                    item.into_css(f)?;
-                    if i!=last {
-                        write!(f," ")?;
+            Self::Attribute{name,test,flag}=>{
+                write!(f,"[{}",name)?;
+                if let Some((operator,value))=test {
+                    write!(f,"{}\"",operator)?;
+                    for c in value.chars() {
+                        if c=='"'||c=='\\' {
+                            f.write_char('\\')?;
+                        }
+                        f.write_char(c)?;
                     }
+                    f.write_char('"')?;
                 }
-                Ok(())
+                if let Some(flag)=flag {
+                    write!(f," {}",flag)?;
+                }
+                f.write_char(']')
             },
             Self::Class(name)=>write!(f,".{}",name),
             Self::Id(name)=>write!(f,"#{}",name),
             Self::Tag(name)=>write!(f,"{}",name),
+            Self::Parent(suffix)=>write!(f,"&{}",suffix),
+            Self::Suffixed(parent,suffix)=>{
+                parent.into_css(f)?;
+                f.write_str(suffix)
+            },
+        }
+    }
+    fn with_parent(&self,parent:&Self)->Option<Self> {
+        match self {
+            Self::Parent(suffix)=>Some(Self::Suffixed(Box::new(parent.clone()),suffix)),
+            Self::List(items)|Self::Sequence(items)|Self::Child(items)|Self::NextSibling(items)|Self::Siblings(items)|
+                Self::Compound(items)|Self::PseudoSelector(_,items)=>{
+                let mut found=false;
+                let items=items.iter()
+                    .map(|item|match item.with_parent(parent) {
+                        Some(item)=>{
+                            found=true;
+                            item
+                        },
+                        None=>item.clone(),
+                    })
+                    .collect();
+                if !found {
+                    return None;
+                }
+                match self {
+                    Self::List(_)=>Some(Self::List(items)),
+                    Self::Child(_)=>Some(Self::Child(items)),
+                    Self::NextSibling(_)=>Some(Self::NextSibling(items)),
+                    Self::Siblings(_)=>Some(Self::Siblings(items)),
+                    Self::Compound(_)=>Some(Self::Compound(items)),
+                    Self::PseudoSelector(name,_)=>Some(Self::PseudoSelector(name,items)),
+                    _=>Some(Self::Sequence(items)),
+                }
+            },
+            _=>None,
+        }
+    }
+    fn replace_pseudo(&mut self,from:&str,to:&'input str)->bool {
+        match self {
+            Self::Pseudo(name)|Self::Parent(name) if name.eq_ignore_ascii_case(from)=>{
+                *name=to;
+                true
+            },
+            Self::Suffixed(parent,suffix)=>{
+                let found=suffix.eq_ignore_ascii_case(from);
+                if found {
+                    *suffix=to;
+                }
+                parent.replace_pseudo(from,to)||found
+            },
+            Self::List(items)|Self::Sequence(items)|Self::Child(items)|Self::NextSibling(items)|Self::Siblings(items)|
+                Self::Compound(items)|Self::PseudoSelector(_,items)=>{
+                let mut found=false;
+                for item in items.iter_mut() {
+                    found|=item.replace_pseudo(from,to);
+                }
+                found
+            },
+            _=>false,
+        }
+    }
+    pub fn nest(&self,parent:&Self)->Self {
+        if let Self::List(parents)=parent {
+            return Self::List(parents.iter().map(|p|self.nest(p)).collect());
+        }
+        if let Self::List(items)=self {
+            return Self::List(items.iter().map(|i|i.nest(parent)).collect());
+        }
+        self.with_parent(parent).unwrap_or_else(||Self::Sequence(vec![parent.clone(),self.clone()]))
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W)->FmtResult {
+        match self {
+            Self::List(items)=>Self::join_into_min_css(items,f,","),
+            Self::Sequence(items)=>Self::join_into_min_css(items,f," "),
+            Self::Child(items)=>Self::join_into_min_css(items,f,">"),
+            Self::NextSibling(items)=>Self::join_into_min_css(items,f,"+"),
+            Self::Siblings(items)=>Self::join_into_min_css(items,f,"~"),
+            Self::Compound(items)=>Self::join_into_min_css(items,f,""),
+            Self::PseudoSelector(name,args)=>{
+                write!(f,"{}(",name)?;
+                Self::join_into_min_css(args,f,",")?;
+                f.write_char(')')
+            },
+            Self::Suffixed(parent,suffix)=>{
+                parent.into_min_css(f)?;
+                f.write_str(suffix)
+            },
+            _=>self.into_css(f),
+        }
+    }
+    fn parse_combined(rest:&'input [Object<'input>])->Result<Vec<Self>,Error> {
+        let mut items=Vec::new();
+        for i in rest {
+            match (i,Self::try_from(i)?) {
+                (Object::List(start,_,end),Self::List(_))=>return Err(Error{start:*start,end:*end,err_type:ErrorType::ListNotAllowed}),
+                (_,item)=>items.push(item),
+            }
+        }
+        Ok(items)
+    }
+    fn parse_compound(rest:&'input [Object<'input>])->Result<Vec<Self>,Error> {
+        let mut items=Vec::new();
+        for (i,raw) in rest.iter().enumerate() {
+            let item=Self::try_from(raw)?;
+            match item {
+                Self::Class(_)|Self::Id(_)|Self::Pseudo(_)|Self::PseudoSelector(..)|Self::PseudoArgs(..)|Self::Attribute{..}=>items.push(item),
+                Self::Tag(_)|Self::Parent(_) if i==0=>items.push(item),
+                _=>{
+                    let (Object::List(start,_,end)|Object::Ident(start,_,end)|Object::Number(start,_,end)|Object::String(start,_,end))=raw;
+                    return Err(Error{start:*start,end:*end,err_type:ErrorType::ExpectedSimpleSelector});
+                },
+            }
+        }
+        Ok(items)
+    }
+    fn parse_attribute(rest:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let is_operator=|o:&Object|matches!(o,Object::Ident(_,op,_) if ATTRIBUTE_OPERATORS.contains(op));
+        let has_element=match rest.len() {
+            2|5=>true,
+            4=>!is_operator(&rest[1]),
+            _=>false,
+        };
+        let (element,rest)=if has_element {
+            (Some(Self::parse_compound(&rest[..1])?),&rest[1..])
+        } else {
+            (None,rest)
+        };
+        let (name,test,flag)=match rest {
+            [Object::Ident(_,name,_)]=>(*name,None,None),
+            [Object::Ident(_,name,_),Object::Ident(_,operator,_),value,flag@..] if ATTRIBUTE_OPERATORS.contains(operator)&&flag.len()<=1=>{
+                let value=match value {
+                    Object::Ident(_,value,_)|Object::Number(_,value,_)=>*value,
+                    Object::String(_,value,_)=>value.as_str(),
+                    Object::List(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::ListNotAllowed}),
+                };
+                let flag=match flag {
+                    [Object::Ident(_,flag,_)] if flag.eq_ignore_ascii_case("i")||flag.eq_ignore_ascii_case("s")=>Some(*flag),
+                    []=>None,
+                    _=>return Err(Error{start,end,err_type:ErrorType::InvalidAttributeSelector}),
+                };
+                (*name,Some((*operator,value)),flag)
+            },
+            _=>return Err(Error{start,end,err_type:ErrorType::InvalidAttributeSelector}),
+        };
+        let attribute=Self::Attribute{name,test,flag};
+        match element {
+            Some(mut items)=>{
+                items.push(attribute);
+                Ok(Self::Compound(items))
+            },
+            None=>Ok(attribute),
+        }
+    }
+    fn parse_pseudo(name:&'input str,start:Location,end:Location)->Result<Self,Error> {
+        if let Some(element)=name.strip_prefix("::") {
+            if is_known(element,PSEUDO_ELEMENTS) {
+                return Ok(Self::Pseudo(name));
+            }
+            Err(Error{start,end,err_type:ErrorType::UnknownPseudoElement})
+        } else if is_known(&name[1..],PSEUDO_CLASSES) {
+            Ok(Self::Pseudo(name))
+        } else {
+            Err(Error{start,end,err_type:ErrorType::UnknownPseudoClass})
+        }
+    }
+    fn parse_pseudo_function(name:&'input str,raw_args:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let (bare,selector_functions,functions,err_type)=match name.strip_prefix("::") {
+            Some(bare)=>(bare,PSEUDO_ELEMENT_SELECTOR_FUNCTIONS,PSEUDO_ELEMENT_FUNCTIONS,ErrorType::UnknownPseudoElement),
+            None=>(&name[1..],PSEUDO_CLASS_SELECTOR_FUNCTIONS,PSEUDO_CLASS_FUNCTIONS,ErrorType::UnknownPseudoClass),
+        };
+        if is_known(bare,selector_functions) {
+            let mut args=Vec::new();
+            for i in raw_args {
+                args.push(i.try_into()?);
+            }
+            Ok(Self::PseudoSelector(name,args))
+        } else if is_known(bare,functions) {
+            let mut args=Vec::new();
+            for i in raw_args {
+                match i {
+                    Object::Ident(_,arg,_)|Object::Number(_,arg,_)=>args.push(*arg),
+                    Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
+                    Object::List(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::ListNotAllowed}),
+                }
+            }
+            Ok(Self::PseudoArgs(name,args))
+        } else {
+            Err(Error{start,end,err_type})
         }
     }
 }
@@ -143,16 +1622,13 @@ impl<'input> TryFrom<&'input Object<'input>> for SelectorType<'input> {
         match o {
             Object::List(start,items,end)=>{
                 match items.as_slice() {
-                    [Object::Ident(_,"seq",_),rest@..]=>{
-                        let mut items=Vec::new();
-                        for i in rest {
-                            match i {
-                                Object::Ident(..)|Object::Number(..)|Object::String(..)=>items.push(Self::try_from(i)?),
-                                Object::List(start,_,end)=>return Err(Error{start:*start,end:*end,err_type:ErrorType::ListNotAllowed}),
-                            }
-                        }
-                        Ok(Self::Sequence(items))
-                    },
+                    [Object::Ident(_,"seq",_),rest@..]=>Ok(Self::Sequence(Self::parse_combined(rest)?)),
+                    [Object::Ident(_,"child",_),rest@..]=>Ok(Self::Child(Self::parse_combined(rest)?)),
+                    [Object::Ident(_,"next",_),rest@..]=>Ok(Self::NextSibling(Self::parse_combined(rest)?)),
+                    [Object::Ident(_,"siblings",_),rest@..]=>Ok(Self::Siblings(Self::parse_combined(rest)?)),
+                    [Object::Ident(_,"and",_),rest@..]=>Ok(Self::Compound(Self::parse_compound(rest)?)),
+                    [Object::Ident(_,"attr",_),rest@..]=>Self::parse_attribute(rest,*start,*end),
+                    [Object::Ident(s,name,e),rest@..] if name.starts_with(':')=>Self::parse_pseudo_function(name,rest,*s,*e),
                     [Object::Ident(_,"list",_),rest@..]=>{
                         let mut items=Vec::new();
                         for i in rest {
@@ -164,8 +1640,12 @@ impl<'input> TryFrom<&'input Object<'input>> for SelectorType<'input> {
                     _=>Err(Error{start:*start,end:*end,err_type:ErrorType::ExpectedSeqOrList}),
                 }
             },
-            Object::Ident(_,name,_)=>{
-                if name.starts_with('#') {
+            Object::Ident(s,name,e)=>{
+                if let Some(suffix)=name.strip_prefix('&') {
+                    Ok(Self::Parent(suffix))
+                } else if name.starts_with(':') {
+                    Self::parse_pseudo(name,*s,*e)
+                } else if name.starts_with('#') {
                     Ok(Self::Id(&name[1..]))
                 } else if name.starts_with('.') {
                     Ok(Self::Class(&name[1..]))
@@ -177,229 +1657,569 @@ impl<'input> TryFrom<&'input Object<'input>> for SelectorType<'input> {
             Object::String(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
         }
     }
-}
-#[derive(Debug)]
-pub struct Rule<'input> {
-    pub selector:SelectorType<'input>,
-    pub inner:Vec<(&'input str,AttributeData<'input>)>,
-}
-impl<'input> Rule<'input> {
-    pub fn into_css<W:Write>(&self,f:&mut W,indent:usize)->FmtResult {
-        for _ in 0..indent {
-            f.write_char(' ')?;
-        }
-        self.selector.into_css(f)?;
-        writeln!(f," {{")?;
-        for i in self.inner.iter() {
-            for _ in 0..indent+4 {
-                f.write_char(' ')?;
-            }
-            write!(f,"{}: ",i.0)?;
-            i.1.into_css(f)?;
-            writeln!(f,";")?;
-        }
-        for _ in 0..indent {
-            f.write_char(' ')?;
-        }
-        writeln!(f,"}}")
+}
+#[derive(Debug,Clone)]
+pub struct Rule<'input> {
+    pub selector:SelectorType<'input>,
+    pub inner:Vec<(&'input str,AttributeData<'input>)>,
+    pub nested:Vec<Item<'input>>,
+    pub uses:Vec<MixinUse<'input>>,
+}
+impl<'input> Rule<'input> {
+    fn from_body(selector:SelectorType<'input>,body:&'input [Object<'input>])->Result<Self,Error> {
+        let mut inner=Vec::new();
+        let mut nested=Vec::new();
+        let mut uses=Vec::new();
+        for i in body {
+            match i {
+                Object::List(s,items,e)=>match items.as_slice() {
+                    [Object::Ident(_,"use",_),Object::Ident(_,name,_),raw_args@..]=>{
+                        let mut args=Vec::new();
+                        for a in raw_args {
+                            args.push(a.try_into()?);
+                        }
+                        uses.push(MixinUse{name,args,position:inner.len(),start:*s,end:*e});
+                    },
+                    [Object::Ident(_,"@media",_),raw_query,rest@..]=>{
+                        let rule=Self::from_body(SelectorType::Parent(""),rest)?;
+                        nested.push(Item::MediaQuery{query:raw_query.try_into()?,inner:vec![Item::Rule(rule)]});
+                    },
+                    [Object::Ident(_,name,_),raw@..] if name.starts_with("--")&&!raw.is_empty()=>{
+                        inner.push((*name,AttributeData::custom_value(raw)?));
+                    },
+                    // `(ul (margin 0))` is a nested rule, but a misspelled `(colr red)` is still a declaration
+                    [Object::Ident(_,name,_),data] if !name.starts_with(NESTED_SELECTOR_STARTS)&&(is_known(name,PROPERTIES)||!matches!(data,Object::List(..)))=>{
+                        inner.push((*name,AttributeData::declaration(name,data)?));
+                    },
+                    [_,_,..]=>nested.push(Item::Rule(i.try_into()?)),
+                    _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
+                },
+                Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
+            }
+        }
+        Ok(Self{selector,inner,nested,uses})
+    }
+    fn expand_mixins(&mut self,constants:&Constants<'input>)->Result<(),Error> {
+        // back to front so the positions of the earlier ones stay valid
+        for mixin_use in self.uses.drain(..).rev() {
+            let declarations=constants.expand_mixin(&mixin_use,0)?;
+            self.inner.splice(mixin_use.position..mixin_use.position,declarations);
+        }
+        Ok(())
+    }
+    pub fn into_css<W:Write>(&self,f:&mut W,options:&RenderOptions,depth:usize)->FmtResult {
+        self.write_resolved(&self.selector,f,options,depth)
+    }
+    fn write_resolved<W:Write>(&self,selector:&SelectorType,f:&mut W,options:&RenderOptions,depth:usize)->FmtResult {
+        if !self.inner.is_empty()||self.nested.is_empty() {
+            options.write_indent(f,depth)?;
+            selector.into_css(f)?;
+            write_declarations(f,&self.inner,options,depth)?;
+        }
+        for item in self.nested.iter() {
+            match item {
+                Item::Rule(rule)=>rule.write_resolved(&rule.selector.nest(selector),f,options,depth)?,
+                Item::MediaQuery{query,inner}=>{
+                    options.write_indent(f,depth)?;
+                    write!(f,"@media ")?;
+                    query.into_css(f,true)?;
+                    writeln!(f," {{")?;
+                    for i in inner {
+                        if let Item::Rule(rule)=i {
+                            rule.write_resolved(&rule.selector.nest(selector),f,options,depth+1)?;
+                        }
+                    }
+                    options.write_indent(f,depth)?;
+                    writeln!(f,"}}")?;
+                },
+                _=>{},
+            }
+        }
+        Ok(())
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W)->FmtResult {
+        self.write_min_resolved(&self.selector,f)
+    }
+    fn write_min_resolved<W:Write>(&self,selector:&SelectorType,f:&mut W)->FmtResult {
+        if !self.inner.is_empty()||self.nested.is_empty() {
+            selector.into_min_css(f)?;
+            f.write_char('{')?;
+            write_min_declarations(f,&self.inner)?;
+            f.write_char('}')?;
+        }
+        for item in self.nested.iter() {
+            match item {
+                Item::Rule(rule)=>rule.write_min_resolved(&rule.selector.nest(selector),f)?,
+                Item::MediaQuery{query,inner}=>{
+                    write!(f,"@media ")?;
+                    query.into_min_css(f,true)?;
+                    f.write_char('{')?;
+                    for i in inner {
+                        if let Item::Rule(rule)=i {
+                            rule.write_min_resolved(&rule.selector.nest(selector),f)?;
+                        }
+                    }
+                    f.write_char('}')?;
+                },
+                _=>{},
+            }
+        }
+        Ok(())
+    }
+}
+#[derive(Debug,Clone)]
+pub struct MixinUse<'input> {
+    pub name:&'input str,
+    pub args:Vec<AttributeData<'input>>,
+    pub position:usize,
+    pub start:Location,
+    pub end:Location,
+}
+#[derive(Debug,Clone)]
+pub struct Mixin<'input> {
+    pub params:Vec<&'input str>,
+    pub declarations:Vec<(&'input str,AttributeData<'input>)>,
+    pub uses:Vec<MixinUse<'input>>,
+}
+impl<'input> Mixin<'input> {
+    fn parse(raw_params:&'input Object<'input>,body:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let mut params=Vec::new();
+        match raw_params {
+            Object::List(_,raw,_)=>{
+                for param in raw {
+                    match param {
+                        Object::Ident(_,name,_) if name.starts_with('$')=>params.push(*name),
+                        Object::List(s,_,e)|Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>{
+                            return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidMixin});
+                        },
+                    }
+                }
+            },
+            Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidMixin}),
+        }
+        let rule=Rule::from_body(SelectorType::Parent(""),body)?;
+        if !rule.nested.is_empty() {
+            return Err(Error{start,end,err_type:ErrorType::InvalidMixin});
+        }
+        Ok(Mixin{params,declarations:rule.inner,uses:rule.uses})
+    }
+}
+impl<'input> TryFrom<&'input Object<'input>> for Rule<'input> {
+    type Error=Error;
+    fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
+        match o {
+            Object::List(start,items,end)=>{
+                match items.as_slice() {
+                    [selector,rest@..]=>Self::from_body(selector.try_into()?,rest),
+                    []=>Err(Error{start:*start,end:*end,err_type:ErrorType::EmptyList}),
+                }
+            },
+            Object::Ident(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
+            Object::Number(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
+            Object::String(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
+        }
+    }
+}
+#[derive(Debug,Clone)]
+pub enum MediaQuery<'input> {
+    List(Vec<Self>),
+    And(Vec<Self>),
+    Or(Vec<Self>),
+    Not(Box<Self>),
+    Only(Box<Self>),
+    Attribute(&'input str,AttributeData<'input>),
+    EmptyAttribute(&'input str),
+    Range {
+        name:&'input str,
+        left:Option<(AttributeData<'input>,&'input str)>,
+        right:Option<(&'input str,AttributeData<'input>)>,
+    },
+    Type(&'input str),
+}
+impl<'input> MediaQuery<'input> {
+    pub fn resolve(&mut self,constants:&Constants<'input>)->Result<(),Error> {
+        match self {
+            Self::List(items)|Self::And(items)|Self::Or(items)=>{
+                for item in items {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::Not(item)|Self::Only(item)=>item.resolve(constants)?,
+            Self::Attribute(_,data)=>data.resolve(constants)?,
+            Self::Range{left,right,..}=>{
+                if let Some((data,_))=left {
+                    data.resolve(constants)?;
+                }
+                if let Some((_,data))=right {
+                    data.resolve(constants)?;
+                }
+            },
+            Self::EmptyAttribute(_)|Self::Type(_)=>{},
+        }
+        Ok(())
+    }
+    pub fn into_css<W:Write>(&self,f:&mut W,first:bool)->FmtResult {
+        self.write(f,first,false)
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W,first:bool)->FmtResult {
+        self.write(f,first,true)
+    }
+    fn write<W:Write>(&self,f:&mut W,first:bool,minify:bool)->FmtResult {
+        match self {
+            Self::List(items)=>{
+                if !first {f.write_char('(')?}
+                for (i,item) in items.iter().enumerate() {
+                    if i!=0 {
+                        f.write_str(if minify {","} else {", "})?;
+                    }
+                    item.write(f,true,minify)?;
+                }
+                if !first {f.write_char(')')?}
+                Ok(())
+            },
+            Self::And(items)|Self::Or(items)=>{
+                let joiner=if let Self::And(_)=self {" and "} else {" or "};
+                if !first {f.write_char('(')?}
+                for (i,item) in items.iter().enumerate() {
+                    if i!=0 {
+                        f.write_str(joiner)?;
+                    }
+                    item.write(f,false,minify)?;
+                }
+                if !first {f.write_char(')')?}
+                Ok(())
+            },
+            Self::Not(item)=>{
+                if !first {f.write_char('(')?}
+                f.write_str("not ")?;
+                // `not` in front of a media type negates the whole query, so it doesn't need parentheses
+                item.write(f,first&&item.starts_with_type(),minify)?;
+                if !first {f.write_char(')')?}
+                Ok(())
+            },
+            Self::Only(item)=>{
+                f.write_str("only ")?;
+                item.write(f,true,minify)
+            },
+            Self::Attribute(name,data)=>{
+                write!(f,"({}:",name)?;
+                if minify {
+                    data.into_min_css(f,false)?;
+                } else {
+                    f.write_char(' ')?;
+                    data.into_css(f)?;
+                }
+                f.write_char(')')
+            },
+            Self::EmptyAttribute(name)=>write!(f,"({})",name),
+            Self::Range{name,left,right}=>{
+                let space=if minify {""} else {" "};
+                f.write_char('(')?;
+                if let Some((data,op))=left {
+                    if minify {data.into_min_css(f,false)?} else {data.into_css(f)?}
+                    write!(f,"{}{}{}",space,op,space)?;
+                }
+                f.write_str(name)?;
+                if let Some((op,data))=right {
+                    write!(f,"{}{}{}",space,op,space)?;
+                    if minify {data.into_min_css(f,false)?} else {data.into_css(f)?}
+                }
+                f.write_char(')')
+            },
+            Self::Type(name)=>f.write_str(name),
+        }
+    }
+    fn starts_with_type(&self)->bool {
+        match self {
+            Self::Type(_)=>true,
+            Self::And(items)=>matches!(items.first(),Some(Self::Type(_))),
+            _=>false,
+        }
+    }
+    fn parse_items(items:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let invalid=Error{start,end,err_type:ErrorType::InvalidMediaQuery};
+        match items {
+            []=>Err(Error{start,end,err_type:ErrorType::EmptyList}),
+            [item]=>item.try_into(),
+            [Object::Ident(_,"only",_),rest@..]=>{
+                let query=Self::parse_items(rest,start,end)?;
+                if !query.starts_with_type() {
+                    return Err(invalid);
+                }
+                Ok(Self::Only(Box::new(query)))
+            },
+            [Object::Ident(_,"not",_),rest@..]=>Ok(Self::Not(Box::new(Self::parse_items(rest,start,end)?))),
+            [_,Object::Ident(_,joiner@("and"|"or"),_),..]=>{
+                let mut list=Vec::new();
+                let mut chunks=items.chunks(2).peekable();
+                while let Some(chunk)=chunks.next() {
+                    match chunk {
+                        [item]=>list.push(item.try_into()?),
+                        // the joiners have to be the same, and there can't be one at the end
+                        [item,Object::Ident(_,j,_)] if j==joiner&&chunks.peek().is_some()=>list.push(item.try_into()?),
+                        _=>return Err(invalid),
+                    }
+                }
+                if *joiner=="and" {Ok(Self::And(list))} else {Ok(Self::Or(list))}
+            },
+            [Object::Ident(_,name,_),Object::Ident(_,op,_),value] if Self::is_feature(name)&&RANGE_OPERATORS.contains(op)=>{
+                Ok(Self::Range{name,left:None,right:Some((op,value.try_into()?))})
+            },
+            [value,Object::Ident(_,op,_),Object::Ident(_,name,_)] if Self::is_feature(name)&&RANGE_OPERATORS.contains(op)=>{
+                Ok(Self::Range{name,left:Some((value.try_into()?,op)),right:None})
+            },
+            [low,Object::Ident(_,low_op,_),Object::Ident(_,name,_),Object::Ident(_,high_op,_),high] if Self::is_feature(name)=>{
+                // both comparisons have to point the same way, and `=` can only be used on its own
+                let ascending=["<","<="].contains(low_op)&&["<","<="].contains(high_op);
+                let descending=[">",">="].contains(low_op)&&[">",">="].contains(high_op);
+                if !(ascending||descending) {
+                    return Err(invalid);
+                }
+                Ok(Self::Range{name,left:Some((low.try_into()?,low_op)),right:Some((high_op,high.try_into()?))})
+            },
+            [Object::Ident(_,name,_),data] if !Self::is_type(name)=>Ok(Self::Attribute(name,data.try_into()?)),
+            _=>{
+                let mut list=Vec::new();
+                for i in items {
+                    let query=i.try_into()?;
+                    // a list can only be at the top of a query
+                    if let Self::List(_)=query {
+                        return Err(invalid);
+                    }
+                    list.push(query);
+                }
+                Ok(Self::List(list))
+            },
+        }
+    }
+    fn is_type(name:&str)->bool {
+        MEDIA_TYPES.iter().any(|t|t.eq_ignore_ascii_case(name))
+    }
+    fn is_feature(name:&str)->bool {
+        !name.starts_with('$')&&!RANGE_OPERATORS.contains(&name)
     }
 }
-impl<'input> TryFrom<&'input Object<'input>> for Rule<'input> {
+impl<'input> TryFrom<&'input Object<'input>> for MediaQuery<'input> {
     type Error=Error;
     fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
         match o {
-            Object::List(start,items,end)=>{
-                match items.as_slice() {
-                    [selector,rest@..]=>{
-                        let mut inner=Vec::new();
-                        for i in rest {
-                            match i {
-                                Object::List(s,items,e)=>match items.as_slice() {
-                                    [Object::Ident(_,name,_),data]=>inner.push((*name,data.try_into()?)),
-                                    _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
-                                },
-                                Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
-                            }
-                        }
-                        Ok(Self{selector:selector.try_into()?,inner})
-                    },
-                    []=>Err(Error{start:*start,end:*end,err_type:ErrorType::EmptyList}),
-                }
-            },
-            Object::Ident(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
+            Object::Ident(_,name,_) if Self::is_type(name)=>Ok(Self::Type(name)),
+            Object::Ident(_,name,_)=>Ok(Self::EmptyAttribute(name)),
+            Object::List(s,items,e)=>Self::parse_items(items,*s,*e),
             Object::Number(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
             Object::String(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
         }
     }
 }
-#[derive(Debug)]
-pub enum MediaQuery<'input> {
-    List(Vec<Self>),
-    And(Box<[Self;2]>),
+#[derive(Debug,Clone)]
+pub enum SupportsCondition<'input> {
+    And(Vec<Self>),
+    Or(Vec<Self>),
     Not(Box<Self>),
-    Attribute(&'input str,AttributeData<'input>),
-    EmptyAttribute(&'input str),
-    Screen,
-    Page,
-    All,
+    Declaration(&'input str,AttributeData<'input>),
+    Selector(SelectorType<'input>),
+    FontTech(&'input str),
+    FontFormat(&'input str),
 }
-impl<'input> MediaQuery<'input> {
+impl<'input> SupportsCondition<'input> {
+    pub fn resolve(&mut self,constants:&Constants<'input>)->Result<(),Error> {
+        match self {
+            Self::And(items)|Self::Or(items)=>{
+                for item in items {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::Not(item)=>item.resolve(constants)?,
+            Self::Declaration(name,data)=>data.resolve_declaration(name,constants)?,
+            Self::Selector(_)|Self::FontTech(_)|Self::FontFormat(_)=>{},
+        }
+        Ok(())
+    }
     pub fn into_css<W:Write>(&self,f:&mut W,first:bool)->FmtResult {
+        self.write(f,first,false)
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W,first:bool)->FmtResult {
+        self.write(f,first,true)
+    }
+    fn write<W:Write>(&self,f:&mut W,first:bool,minify:bool)->FmtResult {
         match self {
-            Self::List(items)=>{
-                if !first {write!(f,"(")?}
-                let last=items.len()-1;
+            Self::And(items)|Self::Or(items)=>{
+                let joiner=if let Self::And(_)=self {" and "} else {" or "};
+                if !first {f.write_char('(')?}
                 for (i,item) in items.iter().enumerate() {
-                    item.into_css(f,false)?;
-                    if i!=last {
-                        write!(f,", ")?;
+                    if i!=0 {
+                        f.write_str(joiner)?;
                     }
+                    item.write(f,false,minify)?;
                 }
-                if !first {write!(f,")")} else {Ok(())}
-            },
-            Self::And(items)=>{
-                if !first {write!(f,"(")?;}
-                items[0].into_css(f,false)?;
-                write!(f," and ")?;
-                items[1].into_css(f,false)?;
-                if !first {write!(f,")")} else {Ok(())}
+                if !first {f.write_char(')')?}
+                Ok(())
             },
             Self::Not(item)=>{
-                if !first {
-                    write!(f,"not ")?;
+                if !first {f.write_char('(')?}
+                f.write_str("not ")?;
+                item.write(f,false,minify)?;
+                if !first {f.write_char(')')?}
+                Ok(())
+            },
+            Self::Declaration(name,data)=>{
+                write!(f,"({}:",name)?;
+                if minify {
+                    data.into_min_css(f,false)?;
                 } else {
-                    write!(f,"(not ")?;
+                    f.write_char(' ')?;
+                    data.into_css(f)?;
                 }
-                item.into_css(f,false)?;
-                write!(f,")")
+                f.write_char(')')
             },
-            Self::EmptyAttribute(attr)=>f.write_str(attr),
-            Self::Attribute(name,data)=>{
-                write!(f,"({}: ",name)?;
-                data.into_css(f)?;
-                write!(f,")")
+            Self::Selector(selector)=>{
+                f.write_str("selector(")?;
+                if minify {selector.into_min_css(f)?} else {selector.into_css(f)?}
+                f.write_char(')')
             },
-            Self::Screen=>write!(f,"screen"),
-            Self::Page=>write!(f,"page"),
-            Self::All=>write!(f,"all"),
+            Self::FontTech(tech)=>write!(f,"font-tech({})",tech),
+            Self::FontFormat(format)=>write!(f,"font-format({})",format),
         }
     }
 }
-impl<'input> TryFrom<&'input Object<'input>> for MediaQuery<'input> {
+impl<'input> TryFrom<&'input Object<'input>> for SupportsCondition<'input> {
     type Error=Error;
     fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
         match o {
-            Object::Ident(_,name,_)=>match *name {
-                "screen"=>Ok(Self::Screen),
-                "all"=>Ok(Self::All),
-                "page"=>Ok(Self::Page),
-                _=>Ok(Self::EmptyAttribute(name)),
-            },
             Object::List(s,items,e)=>{
+                let invalid=Error{start:*s,end:*e,err_type:ErrorType::InvalidSupportsCondition};
                 match items.as_slice() {
-                    [left,Object::Ident(_,"and",_),right]=>Ok(Self::And(Box::new([left.try_into()?,right.try_into()?]))),
-                    [Object::Ident(_,"not",_),item]=>Ok(Self::Not(Box::new(item.try_into()?))),
-                    [Object::Ident(_,name,_),data]=>Ok(Self::Attribute(name,data.try_into()?)),
                     []=>Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
-                    _=>{
+                    [item]=>item.try_into(),
+                    [Object::Ident(_,"not",_),item]=>Ok(Self::Not(Box::new(item.try_into()?))),
+                    [_,Object::Ident(_,joiner@("and"|"or"),_),..]=>{
                         let mut list=Vec::new();
-                        for i in items {
-                            list.push(i.try_into()?);
+                        let mut chunks=items.chunks(2).peekable();
+                        while let Some(chunk)=chunks.next() {
+                            match chunk {
+                                [item]=>list.push(item.try_into()?),
+                                [item,Object::Ident(_,j,_)] if j==joiner&&chunks.peek().is_some()=>list.push(item.try_into()?),
+                                _=>return Err(invalid),
+                            }
                         }
-                        Ok(Self::List(list))
+                        if *joiner=="and" {Ok(Self::And(list))} else {Ok(Self::Or(list))}
                     },
+                    [Object::Ident(_,"selector",_),selector]=>Ok(Self::Selector(selector.try_into()?)),
+                    [Object::Ident(_,"font-tech",_),Object::Ident(_,tech,_)]=>Ok(Self::FontTech(tech)),
+                    [Object::Ident(_,"font-format",_),Object::Ident(_,format,_)]=>Ok(Self::FontFormat(format)),
+                    [Object::Ident(_,name,_),raw@..] if name.starts_with("--")&&!raw.is_empty()=>{
+                        Ok(Self::Declaration(name,AttributeData::custom_value(raw)?))
+                    },
+                    [Object::Ident(_,name,_),data]=>Ok(Self::Declaration(name,AttributeData::declaration(name,data)?)),
+                    _=>Err(invalid),
                 }
             },
+            Object::Ident(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
             Object::Number(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
             Object::String(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
         }
     }
 }
-#[derive(Debug)]
-pub enum KeyframeRule<'input> {
-    Percent {
-        percent:&'input str,
-        attributes:Vec<(&'input str,AttributeData<'input>)>,
-    },
-    From(Vec<(&'input str,AttributeData<'input>)>),
-    To(Vec<(&'input str,AttributeData<'input>)>),
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum KeyframeSelector<'input> {
+    Percent(&'input str),
+    From,
+    To,
 }
-impl<'input> KeyframeRule<'input> {
-    pub fn into_css<W:Write>(&self,f:&mut W,indent:usize)->FmtResult {
-        for _ in 0..indent {
-            f.write_char(' ')?;
+impl<'input> KeyframeSelector<'input> {
+    pub fn into_css<W:Write>(&self,f:&mut W)->FmtResult {
+        match self {
+            Self::Percent(percent)=>f.write_str(percent),
+            Self::From=>f.write_str("from"),
+            Self::To=>f.write_str("to"),
         }
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W)->FmtResult {
         match self {
-            Self::Percent{percent,attributes}=>{
-                writeln!(f,"{} {{",percent)?;
-                for i in attributes {
-                    for _ in 0..indent {
-                        f.write_char(' ')?;
-                    }
-                    write!(f,"{}: ",i.0)?;
-                    i.1.into_css(f)?;
-                    writeln!(f,";")?;
-                }
-                writeln!(f,"}}")
-            },
-            Self::From(attributes)=>{
-                writeln!(f,"from {{")?;
-                for i in attributes {
-                    for _ in 0..indent {
-                        f.write_char(' ')?;
-                    }
-                    write!(f,"{}: ",i.0)?;
-                    i.1.into_css(f)?;
-                    writeln!(f,";")?;
-                }
-                writeln!(f,"}}")
-            },
-            Self::To(attributes)=>{
-                writeln!(f,"to {{")?;
-                for i in attributes {
-                    for _ in 0..indent {
-                        f.write_char(' ')?;
-                    }
-                    write!(f,"{}: ",i.0)?;
-                    i.1.into_css(f)?;
-                    writeln!(f,";")?;
-                }
-                writeln!(f,"}}")
-            },
+            // `0%` is shorter than `from`
+            Self::From=>f.write_str("0%"),
+            _=>self.into_css(f),
         }
     }
 }
-impl<'input> TryFrom<&'input Object<'input>> for KeyframeRule<'input> {
+impl<'input> TryFrom<&'input Object<'input>> for KeyframeSelector<'input> {
     type Error=Error;
     fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
-        fn parse_attrs<'input>(rest:&'input[Object<'input>])->Result<Vec<(&'input str,AttributeData<'input>)>,Error> {
-            let mut attrs:Vec<(&'input str,AttributeData<'input>)>=Vec::new();
-            for i in rest {
-                match i {
-                    Object::List(s,items,e)=>match items.as_slice() {
-                        [Object::Ident(_,name,_),attr]=>attrs.push((name,attr.try_into()?)),
-                        _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
-                    },
-                    Object::Ident(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
-                    Object::Number(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
-                    Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
+        match o {
+            Object::Ident(_,"from",_)=>Ok(Self::From),
+            Object::Ident(_,"to",_)=>Ok(Self::To),
+            Object::Ident(s,percent,e)|Object::Number(s,percent,e)=>{
+                let value=percent.strip_suffix('%')
+                    .and_then(|value|value.parse::<f64>().ok())
+                    .ok_or(Error{start:*s,end:*e,err_type:ErrorType::ExpectedPercent})?;
+                if !(0.0..=100.0).contains(&value) {
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::PercentOutOfRange});
                 }
+                Ok(Self::Percent(percent))
+            },
+            Object::List(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::ListNotAllowed}),
+            Object::String(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
+        }
+    }
+}
+#[derive(Debug,Clone)]
+pub struct KeyframeRule<'input> {
+    pub selectors:Vec<KeyframeSelector<'input>>,
+    pub attributes:Vec<(&'input str,AttributeData<'input>)>,
+}
+impl<'input> KeyframeRule<'input> {
+    pub fn into_css<W:Write>(&self,f:&mut W,options:&RenderOptions,depth:usize)->FmtResult {
+        options.write_indent(f,depth)?;
+        for (i,selector) in self.selectors.iter().enumerate() {
+            if i!=0 {
+                f.write_str(", ")?;
+            }
+            selector.into_css(f)?;
+        }
+        write_declarations(f,&self.attributes,options,depth)
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W)->FmtResult {
+        for (i,selector) in self.selectors.iter().enumerate() {
+            if i!=0 {
+                f.write_char(',')?;
             }
-            return Ok(attrs);
+            selector.into_min_css(f)?;
         }
+        f.write_char('{')?;
+        write_min_declarations(f,&self.attributes)?;
+        f.write_char('}')
+    }
+}
+impl<'input> TryFrom<&'input Object<'input>> for KeyframeRule<'input> {
+    type Error=Error;
+    fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
         match o {
-            Object::List(s,items,e)=>match items.as_slice() {
-                [Object::Ident(_,"from",_),rest@..]=>Ok(Self::From(parse_attrs(rest)?)),
-                [Object::Ident(_,"to",_),rest@..]=>Ok(Self::To(parse_attrs(rest)?)),
-                [Object::Ident(_,percent,_),rest@..]=>{
-                    if percent.ends_with('%') {
-                        Ok(Self::Percent{percent,attributes:parse_attrs(rest)?})
-                    } else {
-                        Err(Error{start:*s,end:*e,err_type:ErrorType::ExpectedPercent})
+            Object::List(s,items,e)=>{
+                let mut selectors=Vec::new();
+                let mut attributes=Vec::new();
+                for i in items {
+                    match i {
+                        Object::List(s,items,e)=>match items.as_slice() {
+                            // the other animation properties are ignored inside of keyframes
+                            [Object::Ident(_,name,_),_] if name.starts_with("animation")&&
+                                !["animation-timing-function","animation-composition"].contains(name)=>
+                            {
+                                return Err(Error{start:*s,end:*e,err_type:ErrorType::NotAllowedInKeyframe});
+                            },
+                            [Object::Ident(_,name,_),attr]=>attributes.push((*name,AttributeData::declaration(name,attr)?)),
+                            _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
+                        },
+                        // the selectors come before the declarations
+                        _ if attributes.is_empty()=>selectors.push(i.try_into()?),
+                        Object::Ident(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
+                        Object::Number(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
+                        Object::String(s,_,e)=>return Err(Error{start:*s,end:*e,err_type:ErrorType::StringNotAllowed}),
                     }
-                },
-                []=>Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
-                _=>Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
+                }
+                if selectors.is_empty() {
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList});
+                }
+                Ok(Self{selectors,attributes})
             },
             Object::Ident(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::IdentNotAllowed}),
             Object::Number(s,_,e)=>Err(Error{start:*s,end:*e,err_type:ErrorType::NumberNotAllowed}),
@@ -407,23 +2227,71 @@ impl<'input> TryFrom<&'input Object<'input>> for KeyframeRule<'input> {
         }
     }
 }
-#[derive(Debug)]
+#[derive(Debug,Clone)]
 pub enum FontValue<'input> {
     Url {
         path:&'input str,
-        format:&'input str,
+        formats:Vec<&'input str>,
+        techs:Vec<&'input str>,
     },
     Local {
         path:&'input str,
-        format:&'input str,
+        format:Option<&'input str>,
     },
 }
 impl<'input> FontValue<'input> {
     pub fn into_css<W:Write>(&self,f:&mut W)->FmtResult {
         match self {
-            Self::Url{path,format}=>write!(f,"url(\"{}\") format(\"{}\")",path,format),
-            Self::Local{path,format}=>write!(f,"local(\"{}\") format(\"{}\")",path,format),
+            Self::Url{path,formats,techs}=>{
+                write!(f,"url(\"{}\")",path)?;
+                if !formats.is_empty() {
+                    f.write_str(" format(")?;
+                    for (i,format) in formats.iter().enumerate() {
+                        if i!=0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f,"\"{}\"",format)?;
+                    }
+                    f.write_char(')')?;
+                }
+                if !techs.is_empty() {
+                    write!(f," tech({})",techs.join(", "))?;
+                }
+                Ok(())
+            },
+            Self::Local{path,format}=>{
+                write!(f,"local(\"{}\")",path)?;
+                if let Some(format)=format {
+                    write!(f," format(\"{}\")",format)?;
+                }
+                Ok(())
+            },
+        }
+    }
+    fn parse_url(path:&'input str,hints:&'input [Object<'input>])->Result<Self,Error> {
+        let mut formats=Vec::new();
+        let mut techs=Vec::new();
+        for hint in hints {
+            match hint {
+                Object::Ident(_,format,_)=>formats.push(*format),
+                Object::List(_,items,_) if matches!(items.first(),Some(Object::Ident(_,"format"|"tech",_)))=>{
+                    let list=if matches!(items[0],Object::Ident(_,"format",_)) {&mut formats} else {&mut techs};
+                    for item in &items[1..] {
+                        match item {
+                            Object::Ident(_,name,_)=>list.push(*name),
+                            Object::String(_,name,_)=>list.push(name.as_str()),
+                            Object::List(s,_,e)|Object::Number(s,_,e)=>{
+                                return Err(Error{start:*s,end:*e,err_type:ErrorType::ExpectedFontValue});
+                            },
+                        }
+                    }
+                },
+                Object::List(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>{
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::ExpectedFontValue});
+                },
+            }
         }
+        Ok(Self::Url{path,formats,techs})
     }
 }
 impl<'input> TryFrom<&'input Object<'input>> for FontValue<'input> {
@@ -431,8 +2299,9 @@ impl<'input> TryFrom<&'input Object<'input>> for FontValue<'input> {
     fn try_from(o:&'input Object<'input>)->Result<Self,Self::Error> {
         match o {
             Object::List(s,items,e)=>match items.as_slice() {
-                [Object::Ident(_,"url",_),Object::String(_,path,_),Object::Ident(_,format,_)]=>Ok(Self::Url{path,format}),
-                [Object::Ident(_,"local",_),Object::String(_,path,_),Object::Ident(_,format,_)]=>Ok(Self::Local{path,format}),
+                [Object::Ident(_,"url",_),Object::String(_,path,_),hints@..]=>Self::parse_url(path,hints),
+                [Object::Ident(_,"local",_),Object::String(_,path,_)]=>Ok(Self::Local{path,format:None}),
+                [Object::Ident(_,"local",_),Object::String(_,path,_),Object::Ident(_,format,_)]=>Ok(Self::Local{path,format:Some(format)}),
                 []=>Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
                 _=>Err(Error{start:*s,end:*e,err_type:ErrorType::ExpectedFontValue}),
             },
@@ -442,13 +2311,14 @@ impl<'input> TryFrom<&'input Object<'input>> for FontValue<'input> {
         }
     }
 }
-#[derive(Debug)]
+#[derive(Debug,Clone)]
 pub enum Item<'input> {
     Rule(Rule<'input>),
     Charset(&'input str),
     FontFace {
         name:&'input str,
         values:Vec<FontValue<'input>>,
+        descriptors:Vec<(&'input str,AttributeData<'input>)>,
     },
     MediaQuery {
         query:MediaQuery<'input>,
@@ -456,115 +2326,575 @@ pub enum Item<'input> {
     },
     Import {
         path:AttributeData<'input>,
+        layer:Option<&'input str>,
         query:Option<MediaQuery<'input>>,
     },
     Keyframes {
+        prefix:&'input str,
         name:&'input str,
         rules:Vec<KeyframeRule<'input>>,
     },
     Supports {
-        does_support:MediaQuery<'input>,
+        does_support:SupportsCondition<'input>,
+        inner:Vec<Self>,
+    },
+    Layer {
+        names:Vec<&'input str>,
+        inner:Option<Vec<Self>>,
+    },
+    Container {
+        name:Option<&'input str>,
+        query:MediaQuery<'input>,
         inner:Vec<Self>,
     },
+    Page {
+        selector:Option<&'input str>,
+        declarations:Vec<(&'input str,AttributeData<'input>)>,
+        margin_boxes:Vec<(&'input str,Vec<(&'input str,AttributeData<'input>)>)>,
+    },
+    Namespace {
+        prefix:Option<&'input str>,
+        url:&'input str,
+    },
+    Property {
+        name:&'input str,
+        syntax:&'input str,
+        inherits:bool,
+        initial_value:Option<AttributeData<'input>>,
+    },
     Comment(&'input str),
+    Raw(&'input str),
+    Define {
+        name:&'input str,
+        value:AttributeData<'input>,
+    },
+    Use(&'input str),
+    Mixin {
+        name:&'input str,
+        mixin:Mixin<'input>,
+    },
 }
 impl<'input> Item<'input> {
-    pub fn into_css<W:Write>(&self,f:&mut W,indent:usize)->FmtResult {
+    pub fn resolve(&mut self,constants:&Constants<'input>)->Result<(),Error> {
+        fn resolve_attrs<'input>(attributes:&mut [(&'input str,AttributeData<'input>)],constants:&Constants<'input>)->Result<(),Error> {
+            for (name,data) in attributes {
+                data.resolve_declaration(name,constants)?;
+            }
+            Ok(())
+        }
+        match self {
+            Self::Rule(rule)=>{
+                rule.expand_mixins(constants)?;
+                resolve_attrs(&mut rule.inner,constants)?;
+                for item in rule.nested.iter_mut() {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::MediaQuery{query,inner}|Self::Container{query,inner,..}=>{
+                query.resolve(constants)?;
+                for item in inner {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::Supports{does_support,inner}=>{
+                does_support.resolve(constants)?;
+                for item in inner {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::Layer{inner:Some(inner),..}=>{
+                for item in inner {
+                    item.resolve(constants)?;
+                }
+            },
+            Self::Page{declarations,margin_boxes,..}=>{
+                resolve_attrs(declarations,constants)?;
+                for (_,declarations) in margin_boxes {
+                    resolve_attrs(declarations,constants)?;
+                }
+            },
+            Self::Property{initial_value:Some(value),..}=>value.resolve(constants)?,
+            Self::Import{path,query,..}=>{
+                path.resolve(constants)?;
+                if let Some(query)=query {
+                    query.resolve(constants)?;
+                }
+            },
+            Self::Keyframes{rules,..}=>{
+                for rule in rules {
+                    resolve_attrs(&mut rule.attributes,constants)?;
+                }
+            },
+            Self::Define{value,..}=>value.resolve(constants)?,
+            Self::FontFace{descriptors,..}=>{
+                for (_,data) in descriptors {
+                    data.resolve(constants)?;
+                }
+            },
+            Self::Layer{inner:None,..}|Self::Property{initial_value:None,..}|Self::Namespace{..}|Self::Charset(_)|
+                Self::Comment(_)|Self::Raw(_)|Self::Use(_)|Self::Mixin{..}=>{},
+        }
+        Ok(())
+    }
+    pub fn local_import(&self)->Option<&'input str> {
+        let path=match self {
+            Self::Import{path:AttributeData::String(path)|AttributeData::Text(path),..}=>path,
+            Self::Import{path:AttributeData::Function{name:"url",args},..}=>match args.as_slice() {
+                [AttributeData::String(path)|AttributeData::Text(path)]=>path,
+                _=>return None,
+            },
+            _=>return None,
+        };
+        is_local_path(path).then_some(path)
+    }
+    fn is_hoisted_import(&self)->bool {
+        match self {
+            Self::Import{..}=>true,
+            Self::Raw(css)=>starts_with_ignore_case(css,"@import"),
+            _=>false,
+        }
+    }
+    fn write_import_layer<W:Write>(f:&mut W,layer:Option<&str>)->FmtResult {
+        match layer {
+            Some("")=>f.write_str(" layer"),
+            Some(name)=>write!(f," layer({})",name),
+            None=>Ok(()),
+        }
+    }
+    fn block(&self)->Option<&[Self]> {
+        match self {
+            Self::MediaQuery{inner,..}|Self::Supports{inner,..}|Self::Container{inner,..}|Self::Layer{inner:Some(inner),..}=>Some(inner),
+            _=>None,
+        }
+    }
+    fn block_mut(&mut self)->Option<&mut Vec<Self>> {
+        match self {
+            Self::MediaQuery{inner,..}|Self::Supports{inner,..}|Self::Container{inner,..}|Self::Layer{inner:Some(inner),..}=>Some(inner),
+            _=>None,
+        }
+    }
+    fn parse_import(raw_path:&'input Object<'input>,rest:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let (layer,rest)=match rest {
+            [Object::Ident(_,"layer",_),rest@..]=>(Some(""),rest),
+            [Object::List(_,items,_),rest@..] if matches!(items.first(),Some(Object::Ident(_,"layer",_)))=>match items.as_slice() {
+                [_,Object::Ident(_,name,_)]=>(Some(*name),rest),
+                _=>return Err(Error{start,end,err_type:ErrorType::InvalidAttribute}),
+            },
+            _=>(None,rest),
+        };
+        let query=match rest {
+            []=>None,
+            [raw_query]=>Some(raw_query.try_into()?),
+            _=>return Err(Error{start,end,err_type:ErrorType::InvalidAttribute}),
+        };
+        Ok(Self::Import{path:raw_path.try_into()?,layer,query})
+    }
+    fn parse_layer(rest:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let mut names=Vec::new();
+        let mut body=rest;
+        while let [Object::Ident(_,name,_),tail@..]=body {
+            names.push(*name);
+            body=tail;
+        }
+        if body.is_empty() {
+            if names.is_empty() {
+                return Err(Error{start,end,err_type:ErrorType::EmptyList});
+            }
+            return Ok(Self::Layer{names,inner:None});
+        }
+        // a block can only have one name
+        if names.len()>1 {
+            return Err(Error{start,end,err_type:ErrorType::InvalidAttribute});
+        }
+        let mut inner=Vec::new();
+        for i in body {
+            inner.push(i.try_into()?);
+        }
+        Ok(Self::Layer{names,inner:Some(inner)})
+    }
+    fn parse_page(rest:&'input [Object<'input>])->Result<Self,Error> {
+        let (selector,body)=match rest {
+            [Object::Ident(_,selector,_),body@..]=>(Some(*selector),body),
+            _=>(None,rest),
+        };
+        let mut declarations=Vec::new();
+        let mut margin_boxes=Vec::new();
+        for i in body {
+            match i {
+                Object::List(s,items,e)=>match items.as_slice() {
+                    [Object::Ident(_,name,_),raw@..] if name.starts_with('@')=>{
+                        if !PAGE_MARGIN_BOXES.iter().any(|b|b.eq_ignore_ascii_case(name)) {
+                            return Err(Error{start:*s,end:*e,err_type:ErrorType::UnknownMarginBox});
+                        }
+                        margin_boxes.push((*name,Self::parse_declarations(raw)?));
+                    },
+                    [Object::Ident(_,name,_),data]=>declarations.push((*name,AttributeData::declaration(name,data)?)),
+                    _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute}),
+                },
+                Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>{
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute});
+                },
+            }
+        }
+        Ok(Self::Page{selector,declarations,margin_boxes})
+    }
+    fn parse_declarations(raw:&'input [Object<'input>])->Result<Vec<(&'input str,AttributeData<'input>)>,Error> {
+        let mut declarations=Vec::new();
+        for i in raw {
+            match i {
+                Object::List(_,items,_) if matches!(items.as_slice(),[Object::Ident(..),_])=>{
+                    if let [Object::Ident(_,name,_),data]=items.as_slice() {
+                        declarations.push((*name,AttributeData::declaration(name,data)?));
+                    }
+                },
+                Object::List(s,_,e)|Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>{
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidAttribute});
+                },
+            }
+        }
+        Ok(declarations)
+    }
+    fn parse_property(name:&'input str,rest:&'input [Object<'input>],start:Location,end:Location)->Result<Self,Error> {
+        let mut syntax=None;
+        let mut inherits=None;
+        let mut initial_value=None;
+        for i in rest {
+            match i {
+                Object::List(s,items,e)=>match items.as_slice() {
+                    [Object::Ident(_,"syntax",_),Object::String(_,value,_)]=>syntax=Some(value.as_str()),
+                    [Object::Ident(_,"inherits",_),Object::Ident(_,"true",_)]=>inherits=Some(true),
+                    [Object::Ident(_,"inherits",_),Object::Ident(_,"false",_)]=>inherits=Some(false),
+                    [Object::Ident(_,"initial-value",_),raw@..] if !raw.is_empty()=>initial_value=Some(AttributeData::custom_value(raw)?),
+                    [Object::Ident(_,"syntax"|"inherits"|"initial-value",_),..]=>{
+                        return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidValue});
+                    },
+                    _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::UnknownDescriptor}),
+                },
+                Object::Ident(s,_,e)|Object::Number(s,_,e)|Object::String(s,_,e)=>{
+                    return Err(Error{start:*s,end:*e,err_type:ErrorType::UnknownDescriptor});
+                },
+            }
+        }
+        let missing=Error{start,end,err_type:ErrorType::MissingDescriptor};
+        let (Some(syntax),Some(inherits))=(syntax,inherits) else {
+            return Err(missing);
+        };
+        // only the universal syntax can go without an initial value
+        if initial_value.is_none()&&syntax.trim()!="*" {
+            return Err(missing);
+        }
+        Ok(Self::Property{name,syntax,inherits,initial_value})
+    }
+    fn write_descriptor<W:Write>(f:&mut W,name:&str,value:&AttributeData,minify:bool)->FmtResult {
+        match value {
+            AttributeData::List(items) if name.eq_ignore_ascii_case("unicode-range")=>{
+                for (i,item) in items.iter().enumerate() {
+                    if i!=0 {
+                        f.write_str(if minify {","} else {", "})?;
+                    }
+                    item.into_css(f)?;
+                }
+                Ok(())
+            },
+            _ if minify=>value.into_min_css(f,false),
+            _=>value.into_css(f),
+        }
+    }
+    fn parse_font_face(name:&'input str,body:&'input [Object<'input>])->Result<Self,Error> {
+        let mut values=Vec::new();
+        let mut descriptors=Vec::new();
+        for i in body {
+            match i {
+                Object::List(_,items,_) if matches!(items.first(),Some(Object::Ident(_,"url"|"local",_)))=>values.push(i.try_into()?),
+                Object::List(s,items,e)=>match items.as_slice() {
+                    [Object::Ident(_,descriptor,_),raw@..] if !raw.is_empty()=>{
+                        if !FONT_FACE_DESCRIPTORS.iter().any(|d|d.eq_ignore_ascii_case(descriptor)) {
+                            return Err(Error{start:*s,end:*e,err_type:ErrorType::UnknownDescriptor});
+                        }
+                        let value=AttributeData::custom_value(raw)?;
+                        if descriptor.eq_ignore_ascii_case("font-display")&&!ValueType::Keyword(FONT_DISPLAY_KEYWORDS).accepts(&value) {
+                            return Err(Error{start:*s,end:*e,err_type:ErrorType::InvalidValue});
+                        }
+                        descriptors.push((*descriptor,value));
+                    },
+                    []=>return Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
+                    _=>return Err(Error{start:*s,end:*e,err_type:ErrorType::ExpectedFontValue}),
+                },
+                _=>values.push(i.try_into()?),
+            }
+        }
+        Ok(Self::FontFace{name,values,descriptors})
+    }
+    fn is_output(&self)->bool {
+        !matches!(self,Self::Define{..}|Self::Use(_)|Self::Mixin{..})
+    }
+    pub fn into_css<W:Write>(&self,f:&mut W,options:&RenderOptions,depth:usize)->FmtResult {
+        if options.minify {
+            return self.into_min_css(f);
+        }
         match self {
-            Self::Rule(rule)=>rule.into_css(f,indent),
+            Self::Rule(rule)=>rule.into_css(f,options,depth),
             Self::Charset(data)=>{
-                for _ in 0..indent {
+                options.write_indent(f,depth)?;
+                writeln!(f,"@charset {};",data)
+            },
+            Self::FontFace{name,values,descriptors}=>{
+                options.write_indent(f,depth)?;
+                writeln!(f,"@font-face {{")?;
+                options.write_indent(f,depth+1)?;
+                writeln!(f,"font-family: \"{}\";",name)?;
+                if !values.is_empty() {
+                    options.write_indent(f,depth+1)?;
+                    write!(f,"src: ")?;
+                    let last=values.len()-1;
+                    for (i,val) in values.iter().enumerate() {
+                        val.into_css(f)?;
+                        if i!=last {
+                            write!(f,", ")?;
+                        }
+                    }
+                    writeln!(f,";")?;
+                }
+                for (name,value) in descriptors {
+                    options.write_indent(f,depth+1)?;
+                    write!(f,"{}: ",name)?;
+                    Self::write_descriptor(f,name,value,false)?;
+                    writeln!(f,";")?;
+                }
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::MediaQuery{query,inner}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"@media ")?;
+                query.into_css(f,true)?;
+                writeln!(f," {{")?;
+                write_items(f,inner,options,depth+1)?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::Import{path,layer,query}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"@import ")?;
+                path.into_css(f)?;
+                Self::write_import_layer(f,*layer)?;
+                if let Some(query)=query {
                     f.write_char(' ')?;
+                    query.into_css(f,true)?;
+                }
+                writeln!(f,";")
+            },
+            Self::Layer{names,inner:None}=>{
+                options.write_indent(f,depth)?;
+                writeln!(f,"@layer {};",names.join(", "))
+            },
+            Self::Layer{names,inner:Some(inner)}=>{
+                options.write_indent(f,depth)?;
+                f.write_str("@layer")?;
+                if let Some(name)=names.first() {
+                    write!(f," {}",name)?;
+                }
+                writeln!(f," {{")?;
+                write_items(f,inner,options,depth+1)?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::Container{name,query,inner}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"@container ")?;
+                if let Some(name)=name {
+                    write!(f,"{} ",name)?;
                 }
-                writeln!(f,"@charset {};",data)
+                query.into_css(f,true)?;
+                writeln!(f," {{")?;
+                write_items(f,inner,options,depth+1)?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
             },
-            Self::FontFace{name,values}=>{
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+            Self::Page{selector,declarations,margin_boxes}=>{
+                options.write_indent(f,depth)?;
+                f.write_str("@page")?;
+                if let Some(selector)=selector {
+                    write!(f," {}",selector)?;
                 }
-                writeln!(f,"@font-face {{")?;
-                for _ in 0..indent+4 {
-                    f.write_char(' ')?;
+                if margin_boxes.is_empty() {
+                    return write_declarations(f,declarations,options,depth);
                 }
-                writeln!(f,"font-family: \"{}\";",name)?;
-                for _ in 0..indent+4 {
-                    f.write_char(' ')?;
+                writeln!(f," {{")?;
+                for (name,data) in declarations {
+                    options.write_indent(f,depth+1)?;
+                    write!(f,"{}: ",name)?;
+                    data.into_css(f)?;
+                    writeln!(f,";")?;
                 }
-                write!(f,"src: ")?;
-                let last=values.len()-1;
-                for (i,val) in values.iter().enumerate() {
-                    val.into_css(f)?;
-                    if i!=last {
-                        write!(f,", ")?;
-                    }
+                for (name,declarations) in margin_boxes {
+                    options.write_indent(f,depth+1)?;
+                    f.write_str(name)?;
+                    write_declarations(f,declarations,options,depth+1)?;
                 }
-                writeln!(f,";")?;
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::Namespace{prefix,url}=>{
+                options.write_indent(f,depth)?;
+                match prefix {
+                    Some(prefix)=>writeln!(f,"@namespace {} url(\"{}\");",prefix,url),
+                    None=>writeln!(f,"@namespace url(\"{}\");",url),
                 }
+            },
+            Self::Property{name,syntax,inherits,initial_value}=>{
+                options.write_indent(f,depth)?;
+                writeln!(f,"@property {} {{",name)?;
+                options.write_indent(f,depth+1)?;
+                writeln!(f,"syntax: \"{}\";",syntax)?;
+                options.write_indent(f,depth+1)?;
+                writeln!(f,"inherits: {};",inherits)?;
+                if let Some(value)=initial_value {
+                    options.write_indent(f,depth+1)?;
+                    f.write_str("initial-value: ")?;
+                    value.into_css(f)?;
+                    writeln!(f,";")?;
+                }
+                options.write_indent(f,depth)?;
                 writeln!(f,"}}")
             },
-            Self::MediaQuery{query,inner}=>{
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+            Self::Keyframes{prefix,name,rules}=>{
+                options.write_indent(f,depth)?;
+                writeln!(f,"@{}keyframes {} {{",prefix,name)?;
+                for rule in rules {
+                    rule.into_css(f,options,depth+1)?;
                 }
-                write!(f,"@media ")?;
-                query.into_css(f,true)?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::Supports{does_support,inner}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"@supports ")?;
+                does_support.into_css(f,true)?;
                 writeln!(f," {{")?;
-                for val in inner.iter() {
-                    val.into_css(f,indent+4)?;
+                write_items(f,inner,options,depth+1)?;
+                options.write_indent(f,depth)?;
+                writeln!(f,"}}")
+            },
+            Self::Comment(data)=>{
+                options.write_indent(f,depth)?;
+                writeln!(f,"/* {} */",data)
+            },
+            Self::Raw(data)=>{
+                for line in data.trim().lines() {
+                    options.write_indent(f,depth)?;
+                    writeln!(f,"{}",line.trim_end())?;
                 }
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+                Ok(())
+            },
+            Self::Define{..}|Self::Use(_)|Self::Mixin{..}=>Ok(()),
+        }
+    }
+    pub fn into_min_css<W:Write>(&self,f:&mut W)->FmtResult {
+        match self {
+            Self::Rule(rule)=>rule.into_min_css(f),
+            Self::Charset(data)=>write!(f,"@charset {};",data),
+            Self::FontFace{name,values,descriptors}=>{
+                write!(f,"@font-face{{font-family:\"{}\"",name)?;
+                for (i,val) in values.iter().enumerate() {
+                    f.write_str(if i==0 {";src:"} else {","})?;
+                    val.into_css(f)?;
                 }
-                writeln!(f,"}}")
+                for (name,value) in descriptors {
+                    write!(f,";{}:",name)?;
+                    Self::write_descriptor(f,name,value,true)?;
+                }
+                f.write_char('}')
             },
-            Self::Import{path,query}=>{
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+            Self::MediaQuery{query,inner}=>{
+                write!(f,"@media ")?;
+                query.into_min_css(f,true)?;
+                f.write_char('{')?;
+                for val in inner.iter() {
+                    val.into_min_css(f)?;
                 }
+                f.write_char('}')
+            },
+            Self::Import{path,layer,query}=>{
                 write!(f,"@import ")?;
-                path.into_css(f)?;
-                write!(f," ")?;
+                path.into_min_css(f,false)?;
+                Self::write_import_layer(f,*layer)?;
                 if let Some(query)=query {
-                    query.into_css(f,true)?;
-                }
-                writeln!(f,";")
-            },
-            Self::Keyframes{name,rules}=>{
-                for _ in 0..indent {
                     f.write_char(' ')?;
+                    query.into_min_css(f,true)?;
                 }
-                writeln!(f,"@keyframes {} {{",name)?;
+                f.write_char(';')
+            },
+            Self::Keyframes{prefix,name,rules}=>{
+                write!(f,"@{}keyframes {}{{",prefix,name)?;
                 for rule in rules {
-                    rule.into_css(f,indent+4)?;
+                    rule.into_min_css(f)?;
                 }
-                for _ in 0..indent {
-                    f.write_char(' ')?;
-                }
-                writeln!(f,"}}")
+                f.write_char('}')
             },
             Self::Supports{does_support,inner}=>{
-                for _ in 0..indent {
-                    f.write_char(' ')?;
-                }
                 write!(f,"@supports ")?;
-                does_support.into_css(f,true)?;
-                writeln!(f," {{")?;
+                does_support.into_min_css(f,true)?;
+                f.write_char('{')?;
                 for item in inner {
-                    item.into_css(f,indent+4)?;
+                    item.into_min_css(f)?;
                 }
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+                f.write_char('}')
+            },
+            Self::Layer{names,inner:None}=>write!(f,"@layer {};",names.join(",")),
+            Self::Layer{names,inner:Some(inner)}=>{
+                f.write_str("@layer")?;
+                if let Some(name)=names.first() {
+                    write!(f," {}",name)?;
                 }
-                writeln!(f,"}}")
+                f.write_char('{')?;
+                for item in inner {
+                    item.into_min_css(f)?;
+                }
+                f.write_char('}')
             },
-            Self::Comment(data)=>{
-                for _ in 0..indent {
-                    f.write_char(' ')?;
+            Self::Container{name,query,inner}=>{
+                write!(f,"@container ")?;
+                if let Some(name)=name {
+                    write!(f,"{} ",name)?;
                 }
-                writeln!(f,"/* {} */",data)
+                query.into_min_css(f,true)?;
+                f.write_char('{')?;
+                for item in inner {
+                    item.into_min_css(f)?;
+                }
+                f.write_char('}')
+            },
+            Self::Page{selector,declarations,margin_boxes}=>{
+                f.write_str("@page")?;
+                if let Some(selector)=selector {
+                    write!(f," {}",selector)?;
+                }
+                f.write_char('{')?;
+                write_min_declarations(f,declarations)?;
+                if !declarations.is_empty()&&!margin_boxes.is_empty() {
+                    f.write_char(';')?;
+                }
+                for (name,declarations) in margin_boxes {
+                    write!(f,"{}{{",name)?;
+                    write_min_declarations(f,declarations)?;
+                    f.write_char('}')?;
+                }
+                f.write_char('}')
+            },
+            Self::Namespace{prefix:Some(prefix),url}=>write!(f,"@namespace {} url(\"{}\");",prefix,url),
+            Self::Namespace{prefix:None,url}=>write!(f,"@namespace url(\"{}\");",url),
+            Self::Property{name,syntax,inherits,initial_value}=>{
+                write!(f,"@property {}{{syntax:\"{}\";inherits:{}",name,syntax,inherits)?;
+                if let Some(value)=initial_value {
+                    f.write_str(";initial-value:")?;
+                    value.into_css(f)?;
+                }
+                f.write_char('}')
             },
+            Self::Comment(data) if data.starts_with('!')=>write!(f,"/*{}*/",data),
+            Self::Raw(data)=>f.write_str(data.trim()),
+            Self::Comment(_)|Self::Define{..}|Self::Use(_)|Self::Mixin{..}=>Ok(()),
         }
     }
 }
@@ -592,31 +2922,47 @@ impl<'input> TryFrom<&'input Object<'input>> for Item<'input> {
                         }
                         Ok(Self::Supports{does_support,inner})
                     },
+                    [Object::Ident(_,"define",_),Object::Ident(_,name,_),raw@..] if name.starts_with('$')&&!raw.is_empty()=>{
+                        Ok(Self::Define{name,value:AttributeData::custom_value(raw)?})
+                    },
+                    [Object::Ident(_,"@use",_),Object::String(_,path,_)]=>Ok(Self::Use(path)),
+                    [Object::Ident(_,"defmixin",_),Object::Ident(_,name,_),raw_params,body@..]=>{
+                        Ok(Self::Mixin{name,mixin:Mixin::parse(raw_params,body,*s,*e)?})
+                    },
                     [Object::Ident(_,"@charset",_),Object::Ident(_,data,_)|Object::Number(_,data,_)]=>Ok(Self::Charset(data)),
                     [Object::Ident(_,"@charset",_),Object::String(_,data,_)]=>Ok(Self::Charset(data)),
-                    [Object::Ident(_,"@import",_),raw_path]=>Ok(Self::Import{path:raw_path.try_into()?,query:None}),
-                    [Object::Ident(_,"@import",_),raw_path,raw_query]=>Ok(Self::Import{path:raw_path.try_into()?,query:Some(raw_query.try_into()?)}),
-                    [Object::Ident(_,"@keyframes",_),Object::Ident(_,name,_),rest@..]=>{
-                        let mut rules=Vec::new();
+                    [Object::Ident(_,"@import",_),raw_path,rest@..]=>Self::parse_import(raw_path,rest,*s,*e),
+                    [Object::Ident(_,"@layer",_),rest@..]=>Self::parse_layer(rest,*s,*e),
+                    [Object::Ident(_,"@container",_),rest@..]=>{
+                        let (name,raw_query,rest)=match rest {
+                            [Object::Ident(_,name,_),raw_query,rest@..]=>(Some(*name),raw_query,rest),
+                            [raw_query,rest@..]=>(None,raw_query,rest),
+                            []=>return Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
+                        };
+                        let query=raw_query.try_into()?;
+                        let mut inner=Vec::new();
                         for i in rest {
-                            rules.push(i.try_into()?);
+                            inner.push(i.try_into()?);
                         }
-                        Ok(Self::Keyframes{name,rules})
+                        Ok(Self::Container{name,query,inner})
                     },
-                    [Object::Ident(_,"@font-face",_),Object::Ident(_,name,_),rest@..]=>{
-                        let mut values=Vec::new();
-                        for i in rest {
-                            values.push(i.try_into()?);
-                        }
-                        Ok(Self::FontFace{name,values})
+                    [Object::Ident(_,"@page",_),rest@..]=>Self::parse_page(rest),
+                    [Object::Ident(_,"@namespace",_),Object::String(_,url,_)]=>Ok(Self::Namespace{prefix:None,url}),
+                    [Object::Ident(_,"@namespace",_),Object::Ident(_,prefix,_),Object::String(_,url,_)]=>{
+                        Ok(Self::Namespace{prefix:Some(prefix),url})
                     },
-                    [Object::Ident(_,"@font-face",_),Object::String(_,name,_),rest@..]=>{
-                        let mut values=Vec::new();
+                    [Object::Ident(_,"@property",_),Object::Ident(_,name,_),rest@..] if name.starts_with("--")=>{
+                        Self::parse_property(name,rest,*s,*e)
+                    },
+                    [Object::Ident(_,at@("@keyframes"|"@-webkit-keyframes"|"@-moz-keyframes"|"@-o-keyframes"),_),Object::Ident(_,name,_),rest@..]=>{
+                        let mut rules=Vec::new();
                         for i in rest {
-                            values.push(i.try_into()?);
+                            rules.push(i.try_into()?);
                         }
-                        Ok(Self::FontFace{name,values})
+                        Ok(Self::Keyframes{prefix:&at[1..at.len()-"keyframes".len()],name,rules})
                     },
+                    [Object::Ident(_,"@font-face",_),Object::Ident(_,name,_),rest@..]=>Self::parse_font_face(name,rest),
+                    [Object::Ident(_,"@font-face",_),Object::String(_,name,_),rest@..]=>Self::parse_font_face(name,rest),
                     []=>Err(Error{start:*s,end:*e,err_type:ErrorType::EmptyList}),
                     _=>Ok(Item::Rule(o.try_into()?)),
                 }
@@ -626,9 +2972,714 @@ impl<'input> TryFrom<&'input Object<'input>> for Item<'input> {
 }
 
 
+
+pub fn write_items<W:Write>(f:&mut W,items:&[Item],options:&RenderOptions,depth:usize)->FmtResult {
+    for (i,item) in items.iter().filter(|i|i.is_output()).enumerate() {
+        if i!=0&&options.blank_lines&&!options.minify {
+            f.write_char('\n')?;
+        }
+        item.into_css(f,options,depth)?;
+    }
+    Ok(())
+}
+
+pub fn inline_imports<'input,F:FnMut(&'input str)->Option<Vec<Item<'input>>>>(items:Vec<Item<'input>>,imported:&mut F)->Vec<Item<'input>> {
+    let mut imports=Vec::new();
+    let mut inlined=Vec::new();
+    for item in items {
+        let Some(mut inner)=item.local_import().and_then(&mut *imported) else {
+            if item.is_hoisted_import() {
+                imports.push(item);
+            } else {
+                inlined.push(item);
+            }
+            continue;
+        };
+        // remote imports in the inlined file have to go to the top too, and only the first `@charset` counts
+        for item in std::mem::take(&mut inner) {
+            match item {
+                _ if item.is_hoisted_import()=>imports.push(item),
+                Item::Charset(_)=>{},
+                _=>inner.push(item),
+            }
+        }
+        if let Item::Import{layer,query,..}=item {
+            if let Some(layer)=layer {
+                let names=if layer.is_empty() {Vec::new()} else {vec![layer]};
+                inner=vec![Item::Layer{names,inner:Some(inner)}];
+            }
+            if let Some(query)=query {
+                inner=vec![Item::MediaQuery{query,inner}];
+            }
+        }
+        inlined.extend(inner);
+    }
+    // `@charset` has to stay first
+    let charsets=inlined.iter().take_while(|item|matches!(item,Item::Charset(_))).count();
+    inlined.splice(charsets..charsets,imports);
+    inlined
+}
+// splits the leading `@import`s off a plain `.css` file so they can be hoisted, erroring on the first local one
+pub fn raw_css_items<'input>(css:&'input str)->Result<Vec<Item<'input>>,&'input str> {
+    let mut items=Vec::new();
+    let mut rest=css;
+    loop {
+        rest=rest.trim_start();
+        if let Some(comment)=rest.strip_prefix("/*") {
+            rest=comment.split_once("*/").map(|(_,rest)|rest).unwrap_or("");
+            continue;
+        }
+        let is_import=starts_with_ignore_case(rest,"@import");
+        if !is_import&&!starts_with_ignore_case(rest,"@charset") {
+            break;
+        }
+        let end=statement_end(rest);
+        let (statement,tail)=rest.split_at(end);
+        rest=tail;
+        if is_import {
+            if let Some(path)=raw_import_path(statement).filter(|path|is_local_path(path)) {
+                return Err(path);
+            }
+            items.push(Item::Raw(statement));
+        }
+    }
+    if !rest.trim().is_empty() {
+        items.push(Item::Raw(rest));
+    }
+    Ok(items)
+}
+fn statement_end(css:&str)->usize {
+    let mut quote=None;
+    for (i,c) in css.char_indices() {
+        match (quote,c) {
+            (Some(q),c) if c==q=>quote=None,
+            (None,'"'|'\'')=>quote=Some(c),
+            (None,';')=>return i+1,
+            _=>{},
+        }
+    }
+    css.len()
+}
+fn raw_import_path(statement:&str)->Option<&str> {
+    let rest=statement["@import".len()..].trim_start();
+    let rest=match rest.get(..4) {
+        Some(url) if url.eq_ignore_ascii_case("url(")=>rest[4..rest.find(')')?].trim(),
+        _=>rest,
+    };
+    let quote=rest.chars().next()?;
+    if quote=='"'||quote=='\'' {
+        rest[1..].split(quote).next()
+    } else {
+        rest.split(|c:char|c.is_whitespace()||c==';').next()
+    }
+}
+fn is_local_path(path:&str)->bool {
+    let remote=path.contains("://")||path.starts_with("//")||path.starts_with("data:");
+    !remote&&(path.ends_with(".cssx")||path.ends_with(".css"))
+}
+fn starts_with_ignore_case(text:&str,prefix:&str)->bool {
+    text.get(..prefix.len()).is_some_and(|start|start.eq_ignore_ascii_case(prefix))
+}
+
+#[derive(Debug,Default,Clone)]
+pub struct Constants<'input> {
+    values:HashMap<&'input str,AttributeData<'input>>,
+    mixins:HashMap<&'input str,Mixin<'input>>,
+}
+impl<'input> Constants<'input> {
+    pub fn add_items(&mut self,items:&[Item<'input>])->Result<(),Error> {
+        for item in items {
+            match item {
+                Item::Define{name,value}=>{
+                    let mut value=value.clone();
+                    value.resolve(self)?;
+                    self.values.insert(name,value);
+                },
+                Item::Mixin{name,mixin}=>{
+                    self.mixins.insert(name,mixin.clone());
+                },
+                _=>{},
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self,name:&str)->Option<AttributeData<'input>> {
+        self.values.get(name).cloned()
+    }
+    fn expand_mixin(&self,mixin_use:&MixinUse<'input>,depth:usize)->Result<Vec<(&'input str,AttributeData<'input>)>,Error> {
+        let err=|err_type|Error{start:mixin_use.start,end:mixin_use.end,err_type};
+        if depth>MAX_MIXIN_DEPTH {
+            return Err(err(ErrorType::MixinRecursion));
+        }
+        let mixin=self.mixins.get(mixin_use.name).ok_or_else(||err(ErrorType::UndefinedMixin))?;
+        if mixin.params.len()!=mixin_use.args.len() {
+            return Err(err(ErrorType::WrongArgumentCount));
+        }
+        let mut scope=self.clone();
+        for (param,arg) in mixin.params.iter().zip(mixin_use.args.iter()) {
+            let mut arg=arg.clone();
+            arg.resolve(self)?;
+            scope.values.insert(param,arg);
+        }
+        let mut declarations=mixin.declarations.clone();
+        for (name,data) in declarations.iter_mut() {
+            data.resolve_declaration(name,&scope)?;
+        }
+        for inner_use in mixin.uses.iter().rev() {
+            let mut inner_use=inner_use.clone();
+            for arg in inner_use.args.iter_mut() {
+                arg.resolve(&scope)?;
+            }
+            let inner=self.expand_mixin(&inner_use,depth+1)?;
+            declarations.splice(inner_use.position..inner_use.position,inner);
+        }
+        Ok(declarations)
+    }
+    pub fn resolve_items(&self,items:&mut [Item<'input>])->Result<(),Error> {
+        for item in items {
+            item.resolve(self)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct CustomProperties {
+    declared:HashSet<String>,
+    used:Vec<(String,String)>,
+}
+impl CustomProperties {
+    pub fn add_items(&mut self,items:&[Item],source:&str) {
+        for_each_declaration(items,&mut |name,data|{
+            if name.starts_with("--") {
+                self.declared.insert(name.to_string());
+            }
+            data.for_each_var(&mut |var|{
+                let var=if var.starts_with("--") {var.to_string()} else {format!("--{}",var)};
+                if !self.used.iter().any(|(used,used_source)|*used==var&&used_source==source) {
+                    self.used.push((var,source.to_string()));
+                }
+            });
+        });
+        self.add_registered(items);
+    }
+    fn add_registered(&mut self,items:&[Item]) {
+        for item in items {
+            match item {
+                Item::Property{name,..}=>{
+                    self.declared.insert(name.to_string());
+                },
+                _=>if let Some(inner)=item.block() {
+                    self.add_registered(inner);
+                },
+            }
+        }
+    }
+    pub fn undeclared(&self)->impl Iterator<Item=(&str,&str)> {
+        self.used.iter()
+            .filter(|(name,_)|!self.declared.contains(name))
+            .map(|(name,source)|(name.as_str(),source.as_str()))
+    }
+}
+
+
+pub fn lower_items(items:&mut [Item],targets:&Targets) {
+    for item in items.iter_mut() {
+        if let Some(inner)=item.block_mut() {
+            lower_items(inner,targets);
+        }
+        match item {
+            Item::Rule(rule)=>{
+                lower_declarations(&mut rule.inner,targets);
+                lower_items(&mut rule.nested,targets);
+            },
+            Item::Keyframes{rules,..}=>{
+                for rule in rules.iter_mut() {
+                    lower_declarations(&mut rule.attributes,targets);
+                }
+            },
+            _=>{},
+        }
+    }
+}
+fn lower_declarations<'input>(attributes:&mut Vec<(&'input str,AttributeData<'input>)>,targets:&Targets) {
+    let mut lowered=Vec::new();
+    for (name,value) in attributes.drain(..) {
+        // the most compatible value comes first
+        let mut values=Vec::new();
+        if targets.needs(MIN_MAX) {
+            values.extend(value.replace_values(&AttributeData::clamp_upper_bound));
+        }
+        if targets.needs(CLAMP) {
+            values.extend(value.replace_values(&AttributeData::clamp_as_min_max));
+        }
+        if targets.needs(LAB_COLORS) {
+            for v in values.iter_mut() {
+                if let Some(srgb)=v.replace_values(&AttributeData::lab_as_srgb) {
+                    *v=srgb;
+                }
+            }
+            values.extend(value.replace_values(&AttributeData::lab_as_srgb));
+        }
+        let physical=LOGICAL_TO_PHYSICAL.iter().find(|(logical,_)|logical.eq_ignore_ascii_case(name));
+        // the physical fallback assumes left-to-right text, so browsers that also understand the logical property
+        // would apply both in right-to-left text. It's only added when none of the targets support them.
+        if let (Some((_,physical)),true)=(physical,targets.needs_everywhere(LOGICAL_PROPERTIES)) {
+            let fallback=values.first().unwrap_or(&value);
+            match physical {
+                [single]=>lowered.push((*single,fallback.clone())),
+                [first,second]=>{
+                    let (first_value,second_value)=fallback.split_pair();
+                    lowered.push((*first,first_value));
+                    lowered.push((*second,second_value));
+                },
+                _=>{},
+            }
+        }
+        lowered.extend(values.into_iter().map(|v|(name,v)));
+        lowered.push((name,value));
+    }
+    *attributes=lowered;
+}
+// values like `-webkit-box` use older syntax instead of just a prefix, so they are left alone
+pub fn prefix_items(items:&mut Vec<Item>,targets:&Targets) {
+    let written_selectors=items.iter()
+        .filter_map(|item|match item {
+            Item::Rule(rule)=>Some(min_selector(&rule.selector)),
+            _=>None,
+        })
+        .collect::<Vec<_>>();
+    let written_keyframes=items.iter()
+        .filter_map(|item|match item {
+            Item::Keyframes{prefix,name,..}=>Some((prefix.to_string(),name.to_string())),
+            _=>None,
+        })
+        .collect::<Vec<_>>();
+    let mut prefixed=Vec::new();
+    for mut item in items.drain(..) {
+        if let Some(inner)=item.block_mut() {
+            prefix_items(inner,targets);
+        }
+        match &mut item {
+            Item::Rule(rule)=>{
+                prefix_declarations(&mut rule.inner,targets);
+                prefix_items(&mut rule.nested,targets);
+                let unneeded=PREFIXED_PSEUDOS.iter()
+                    .any(|(_,with_prefix,since)|!targets.needs(since)&&rule.selector.clone().replace_pseudo(with_prefix,""));
+                if unneeded {
+                    continue;
+                }
+                // an unknown pseudo-class makes the whole rule invalid, so each prefix gets its own rule
+                for (without_prefix,with_prefix,since) in PREFIXED_PSEUDOS {
+                    let mut copy=rule.clone();
+                    let needed=targets.needs(since)&&copy.selector.replace_pseudo(without_prefix,with_prefix);
+                    if needed&&!written_selectors.contains(&min_selector(&copy.selector)) {
+                        prefixed.push(Item::Rule(copy));
+                    }
+                }
+            },
+            Item::Keyframes{prefix,rules,..}=>{
+                for rule in rules.iter_mut() {
+                    prefix_declarations(&mut rule.attributes,targets);
+                }
+                if !prefix.is_empty() {
+                    let needed=PREFIXED_KEYFRAMES.iter().any(|(p,since)|p==prefix&&targets.needs(since));
+                    if !needed {
+                        continue;
+                    }
+                } else {
+                    for (with_prefix,since) in PREFIXED_KEYFRAMES {
+                        if let (true,Item::Keyframes{name,rules,..})=(targets.needs(since),&item) {
+                            if !written_keyframes.iter().any(|(p,n)|p==with_prefix&&n==name) {
+                                prefixed.push(Item::Keyframes{prefix:with_prefix,name,rules:rules.clone()});
+                            }
+                        }
+                    }
+                }
+            },
+            _=>{},
+        }
+        prefixed.push(item);
+    }
+    *items=prefixed;
+}
+fn min_selector(selector:&SelectorType)->String {
+    let mut out=String::new();
+    selector.into_min_css(&mut out).unwrap();
+    out
+}
+fn prefix_declarations(attributes:&mut Vec<(&str,AttributeData)>,targets:&Targets) {
+    let written=attributes.iter().map(|(name,_)|name.to_ascii_lowercase()).collect::<Vec<_>>();
+    let mut prefixed=Vec::new();
+    for (name,value) in attributes.drain(..) {
+        let unneeded=PREFIXED_PROPERTIES.iter()
+            .any(|(_,with_prefix,since)|with_prefix.eq_ignore_ascii_case(name)&&!targets.needs(since));
+        if unneeded {
+            continue;
+        }
+        for (without_prefix,with_prefix,since) in PREFIXED_PROPERTIES {
+            if without_prefix.eq_ignore_ascii_case(name)&&targets.needs(since)&&!written.iter().any(|w|w==with_prefix) {
+                prefixed.push((*with_prefix,value.clone()));
+            }
+        }
+        prefixed.push((name,value));
+    }
+    *attributes=prefixed;
+}
+
+
+#[derive(Debug)]
+pub enum Lint<'input> {
+    MisspelledFunction {
+        name:&'input str,
+        suggestion:&'static str,
+    },
+    UnknownProperty {
+        name:&'input str,
+        suggestion:Option<&'static str>,
+    },
+}
+impl<'input> Display for Lint<'input> {
+    fn fmt(&self,f:&mut Formatter)->FmtResult {
+        match self {
+            Self::MisspelledFunction{name,suggestion}=>{
+                write!(f,"`({} ...)` is written as a list. Did you mean the `{}` function?",name,suggestion)
+            },
+            Self::UnknownProperty{name,suggestion:Some(suggestion)}=>{
+                write!(f,"unknown property `{}`. Did you mean `{}`?",name,suggestion)
+            },
+            Self::UnknownProperty{name,suggestion:None}=>write!(f,"unknown property `{}`",name),
+        }
+    }
+}
+pub fn lint_items<'input>(items:&[Item<'input>])->Vec<Lint<'input>> {
+    let mut lints=Vec::new();
+    for_each_declaration(items,&mut |name,data|{
+        if !is_known(name,PROPERTIES) {
+            let seen=lints.iter().any(|l|matches!(l,Lint::UnknownProperty{name:seen,..} if *seen==name));
+            if !seen {
+                lints.push(Lint::UnknownProperty{name,suggestion:suggest(name,PROPERTIES)});
+            }
+        }
+        data.for_each_list(&mut |list|{
+            if let Some(AttributeData::Text(name))=list.first() {
+                if !name.chars().all(|c|c.is_ascii_alphabetic()||c=='-')||is_keyword(name) {
+                    return;
+                }
+                if let Some(suggestion)=suggest(name,CSS_FUNCTIONS) {
+                    let seen=lints.iter().any(|l|matches!(l,Lint::MisspelledFunction{name:seen,..} if seen==name));
+                    if !seen {
+                        lints.push(Lint::MisspelledFunction{name,suggestion});
+                    }
+                }
+            }
+        });
+    });
+    lints
+}
+
+
 #[derive(Debug)]
 pub struct Error {
     pub start:Location,
     pub end:Location,
     pub err_type:ErrorType,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use s_expression_parser::File as SFile;
+    use super::*;
+    use crate::render::Indent;
+
+    fn compile(source:&str,options:&RenderOptions)->Result<String,Error> {
+        let file=SFile::parse_file(source).unwrap();
+        let mut items=Vec::new();
+        for i in file.items.iter() {
+            items.push(Item::try_from(i)?);
+        }
+        let mut constants=Constants::default();
+        constants.add_items(&items)?;
+        constants.resolve_items(&mut items)?;
+        let mut out=String::new();
+        write_items(&mut out,&items,options,0).unwrap();
+        Ok(out)
+    }
+    fn pretty(source:&str)->String {
+        compile(source,&RenderOptions::default()).unwrap()
+    }
+    fn minified(source:&str)->String {
+        compile(source,&RenderOptions{minify:true,..RenderOptions::default()}).unwrap()
+    }
+
+    #[test]
+    fn property_names_are_not_functions() {
+        assert_eq!(minified("(.a (transition (scale 0.3s ease)))"),".a{transition:scale .3s ease}");
+        assert_eq!(minified("(.a (transition-property (rotate opacity)))"),".a{transition-property:rotate opacity}");
+        assert_eq!(minified("(.a (transform (fn scale 2)))"),".a{transform:scale(2)}");
+        assert_eq!(pretty("(.a (transition (scale 0.3s ease)))"),".a {\n    transition: scale 0.3s ease;\n}\n");
+    }
+    #[test]
+    fn minified_zero_units() {
+        assert_eq!(minified("(.a (margin (0px 0em)) (flex (1 0px)) (flex-basis 0px) (width (+ 0px 1em)))"),
+            ".a{margin:0 0;flex:1 0px;flex-basis:0px;width:calc(0px + 1em)}");
+    }
+    #[test]
+    fn valid_values_pass_the_type_check() {
+        let valid=[
+            "(.a (font-style (oblique 10deg)))",
+            "(.a (white-space (preserve nowrap)))",
+            "(.a (white-space (collapse discard-before discard-after)))",
+            "(.a (text-transform (uppercase full-width full-size-kana)))",
+            "(.a (text-indent (1em hanging each-line)))",
+            "(.a (text-align (\".\" center)))",
+            "(.a (caret-color auto) (accent-color auto) (outline-color invert))",
+            "(.a (margin (0 auto)) (padding (1px 2px 3px 4px)) (gap (1px 2em)))",
+            "(.a (border-radius (10px 5% / 20px)) (display (inline flow-root)) (overflow (hidden auto)))",
+            "(.a (clear block-start) (border-style (solid none dashed)))",
+            "(.a (width (var w)) (color inherit) (margin (1px (var m))) (display (env x)))",
+        ];
+        for source in valid {
+            assert!(compile(source,&RenderOptions::default()).is_ok(),"{} was rejected",source);
+        }
+    }
+    #[test]
+    fn invalid_values_are_rejected() {
+        for source in ["(.a (width red))","(.a (margin (1px 2px 3px 4px 5px)))","(.a (color 10px))","(.a (display blue))"] {
+            let err=compile(source,&RenderOptions::default()).unwrap_err();
+            assert!(matches!(err.err_type,ErrorType::InvalidValue),"{} wasn't rejected",source);
+        }
+    }
+    #[test]
+    fn raw_css_imports_are_split_off() {
+        let items=raw_css_items("@charset \"utf-8\";\n/* x */ @import url(\"https://a.b/c.css\") screen;\n.a{color:red}").unwrap();
+        assert!(matches!(items.as_slice(),[Item::Raw("@import url(\"https://a.b/c.css\") screen;"),Item::Raw(".a{color:red}")]));
+        assert_eq!(raw_css_items("@import \"b.css\";.a{}").err(),Some("b.css"));
+        assert_eq!(raw_css_items("@import url(./b.css);").err(),Some("./b.css"));
+    }
+    #[test]
+    fn inlined_imports_are_hoisted() {
+        let file=SFile::parse_file("(.a (color red)) (@import \"b.css\")").unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let bundled=inline_imports(items,&mut |path|{
+            assert_eq!(path,"b.css");
+            Some(raw_css_items("@charset \"utf-8\";@import url(https://a.b/c.css);.b{color:blue}").unwrap())
+        });
+        let mut out=String::new();
+        write_items(&mut out,&bundled,&RenderOptions{minify:true,..RenderOptions::default()},0).unwrap();
+        assert_eq!(out,"@import url(https://a.b/c.css);.a{color:red}.b{color:blue}");
+    }
+    #[test]
+    fn nested_rules_are_flattened() {
+        assert_eq!(minified("(.a (color red) (&:hover (color blue)) (.b (margin 0)))"),".a{color:red}.a:hover{color:blue}.a .b{margin:0}");
+        assert_eq!(minified("((list .a .b) (.c (color red)))"),".a .c,.b .c{color:red}");
+        assert_eq!(minified("(.a (@media print (color red)))"),"@media print{.a{color:red}}");
+    }
+    #[test]
+    fn bare_tags_nest() {
+        assert_eq!(minified("(nav (ul (margin 0)) (color red))"),"nav{color:red}nav ul{margin:0}");
+        assert_eq!(minified("(nav (ul (li (padding 1px))))"),"nav ul li{padding:1px}");
+        assert_eq!(minified("(nav (margin (0 auto)) (-webkit-box-flex 1) (--gap (1px 2px)))"),"nav{margin:0 auto;-webkit-box-flex:1;--gap:1px 2px}");
+    }
+    #[test]
+    fn written_prefixes_are_not_repeated() {
+        let prefixed=|source:&str|{
+            let file=SFile::parse_file(source).unwrap();
+            let mut items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+            prefix_items(&mut items,&Targets::parse("firefox 50").unwrap());
+            let mut out=String::new();
+            write_items(&mut out,&items,&RenderOptions{minify:true,..RenderOptions::default()},0).unwrap();
+            out
+        };
+        assert_eq!(prefixed("(::selection (color red))"),"::-moz-selection{color:red}::selection{color:red}");
+        assert_eq!(prefixed("(::-moz-selection (color blue)) (::selection (color red))"),"::-moz-selection{color:blue}::selection{color:red}");
+        assert_eq!(prefixed("(.a (-moz-columns 2) (columns 3))"),".a{-moz-columns:2;columns:3}");
+        assert_eq!(prefixed("(.a (display -webkit-box))"),".a{display:-webkit-box}");
+    }
+    #[test]
+    fn minified_values() {
+        assert_eq!(minified("\"! license\" \"note\" (.a (color #FFffFF) (opacity 0.5) (margin (0.5em -0.25em)) (background (url \"#aabbcc\")))"),
+            "/*! license*/.a{color:#fff;opacity:.5;margin:.5em -.25em;background:url(\"#aabbcc\")}");
+    }
+    #[test]
+    fn render_options() {
+        let source="(.a (color red) (margin 0)) (@media print (.b (color blue)))";
+        let render=|options:RenderOptions|compile(source,&options).unwrap();
+        assert_eq!(render(RenderOptions{indent:Indent::Tabs,..RenderOptions::default()}),
+            ".a {\n\tcolor: red;\n\tmargin: 0;\n}\n@media print {\n\t.b {\n\t\tcolor: blue;\n\t}\n}\n");
+        assert_eq!(render(RenderOptions{indent:Indent::Spaces(2),blank_lines:true,..RenderOptions::default()}),
+            ".a {\n  color: red;\n  margin: 0;\n}\n\n@media print {\n  .b {\n    color: blue;\n  }\n}\n");
+        assert_eq!(render(RenderOptions{compact_rules:true,..RenderOptions::default()}),
+            ".a { color: red; margin: 0; }\n@media print {\n    .b { color: blue; }\n}\n");
+    }
+    #[test]
+    fn combinators() {
+        assert_eq!(minified("((child ul li) (color red))"),"ul>li{color:red}");
+        assert_eq!(minified("((next h1 p) (color red))"),"h1+p{color:red}");
+        assert_eq!(minified("((siblings h1 p) (color red))"),"h1~p{color:red}");
+        assert_eq!(minified("((seq nav (child ul li)) (color red))"),"nav ul>li{color:red}");
+        assert_eq!(minified("((list (child a b) .c) (color red))"),"a>b,.c{color:red}");
+        assert_eq!(pretty("((child ul li) (color red))"),"ul > li {\n    color: red;\n}\n");
+    }
+    #[test]
+    fn compound_and_pseudo_selectors() {
+        assert_eq!(minified("((and button.primary #go :hover) (color red))"),"button.primary#go:hover{color:red}");
+        assert_eq!(minified("(a::before (color red))"),"a::before{color:red}");
+        assert_eq!(minified("((:not .a (and .b :focus)) (color red))"),":not(.a,.b:focus){color:red}");
+        assert_eq!(minified("((:nth-child 2n+1) (color red)) ((::slotted span) (color red))"),":nth-child(2n+1){color:red}::slotted(span){color:red}");
+        assert!(matches!(compile("(:hovr (color red))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::UnknownPseudoClass));
+        assert!(matches!(compile("((and .a button) (color red))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::ExpectedSimpleSelector));
+    }
+    #[test]
+    fn attribute_selectors() {
+        assert_eq!(minified("((attr disabled) (color red))"),"[disabled]{color:red}");
+        assert_eq!(minified("((attr input type = text) (color red))"),"input[type=\"text\"]{color:red}");
+        assert_eq!(minified("((attr a href ^= \"https\" i) (color red))"),"a[href^=\"https\" i]{color:red}");
+        assert_eq!(minified("((attr lang |= en s) (color red))"),"[lang|=\"en\" s]{color:red}");
+        assert!(matches!(compile("((attr a href == x) (color red))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::InvalidAttributeSelector));
+    }
+    #[test]
+    fn custom_properties() {
+        assert_eq!(minified("(:root (--brand #f00) (--gap (1px 2px)))"),":root{--brand:#f00;--gap:1px 2px}");
+        assert_eq!(minified("(.a (color (var brand (var other red))) (margin (var --gap 0)))"),".a{color:var(--brand,var(--other,red));margin:var(--gap,0)}");
+        let file=SFile::parse_file("(:root (--brand red)) (.a (color (var brand)) (margin (var gap)))").unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let mut properties=CustomProperties::default();
+        properties.add_items(&items,"a.cssx");
+        assert_eq!(properties.undeclared().collect::<Vec<_>>(),[("--gap","a.cssx")]);
+    }
+    #[test]
+    fn constants() {
+        assert_eq!(minified("(define $brand #ff0000) (.a (color $brand) (border (1px solid $brand)))"),".a{color:#f00;border:1px solid #f00}");
+        assert_eq!(minified("(define $a 2px) (define $b (+ $a 1px)) (.a (margin $b))"),".a{margin:3px}");
+        assert!(matches!(compile("(.a (color $missing))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::UndefinedConstant));
+        assert!(matches!(compile("(define $w red) (.a (width $w))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::InvalidValue));
+    }
+    #[test]
+    fn mixins() {
+        assert_eq!(minified("(defmixin pad ($x $y) (padding ($x $y)) (margin 0)) (.a (use pad 1px 2px) (color red))"),".a{padding:1px 2px;margin:0;color:red}");
+        let error=|source|compile(source,&RenderOptions::default()).unwrap_err().err_type;
+        assert!(matches!(error("(defmixin pad ($x) (padding $x)) (.a (use pad))"),ErrorType::WrongArgumentCount));
+        assert!(matches!(error("(.a (use nothing))"),ErrorType::UndefinedMixin));
+        assert!(matches!(error("(defmixin a () (use a)) (.b (use a))"),ErrorType::MixinRecursion));
+    }
+    #[test]
+    fn math_is_folded() {
+        assert_eq!(minified("(.a (width (+ 10px 2px)) (height (* 2 3em)) (margin (/ 10px 4)) (padding (- 1em 0.5em)))"),".a{width:12px;height:6em;margin:2.5px;padding:.5em}");
+        assert_eq!(minified("(.a (width (+ 10px 2rem)) (height (+ 50% 10px)) (top (* (+ 1px 2px) 2)))"),".a{width:calc(10px + 2rem);height:calc(50% + 10px);top:6px}");
+        assert_eq!(minified("(.a (width (+ 1px (* 2 1em))) (height (calc (+ 1px 1em))))"),".a{width:calc(1px + 2em);height:calc(1px + 1em)}");
+        assert_eq!(pretty("(.a (width (+ 1px (* 2 1em))))"),".a {\n    width: calc(1px + 2em);\n}\n");
+        let error=|source|compile(source,&RenderOptions::default()).unwrap_err().err_type;
+        assert!(matches!(error("(.a (width (+ 10px 2s)))"),ErrorType::IncompatibleUnits));
+        assert!(matches!(error("(.a (width (/ 1px 0)))"),ErrorType::DivisionByZero));
+    }
+    #[test]
+    fn color_helpers() {
+        assert_eq!(minified("(.a (color (lighten #336699 10%)) (background (darken red 0.2)))"),".a{color:#4080bf;background:#900}");
+        assert_eq!(minified("(.a (color (mix red blue)) (background (mix #000 #fff 25%)))"),".a{color:#800080;background:#bfbfbf}");
+        assert_eq!(minified("(.a (color (alpha #f00 50%)) (background (lighten (hsl 120 50% 50%) 10%)))"),".a{color:#ff000080;background:#6c6}");
+        assert_eq!(minified("(define $b #123456) (.a (color (darken $b 5%)))"),".a{color:#0e2741}");
+        let error=|source|compile(source,&RenderOptions::default()).unwrap_err().err_type;
+        assert!(matches!(error("(.a (color (lighten notacolor 10%)))"),ErrorType::InvalidColor));
+        assert!(matches!(error("(.a (color #ggg))"),ErrorType::InvalidColor));
+        assert!(matches!(error("(.a (color (mix red)))"),ErrorType::WrongArgumentCount));
+    }
+    #[test]
+    fn known_functions() {
+        assert_eq!(minified("(.a (color (rgb 0 0 0 / 50%)) (background (rgba 0 0 0 0.5)) (transform (translatex 10px)))"),
+            ".a{color:rgb(0 0 0 / 50%);background:rgba(0,0,0,.5);transform:translatex(10px)}");
+        assert_eq!(minified("(.a (width (fn foo 1 2)) (grid-template-columns (fn repeat 3 1fr)))"),".a{width:foo(1,2);grid-template-columns:repeat(3,1fr)}");
+        let file=SFile::parse_file("(.a (box-shadow (rbg 0 0 0)) (margin (auto auto)))").unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let lints=lint_items(&items);
+        assert!(matches!(lints.as_slice(),[Lint::MisspelledFunction{name:"rbg",suggestion:"rgb"}]),"{:?}",lints);
+    }
+    #[test]
+    fn unknown_properties() {
+        let file=SFile::parse_file("(.a (colr red) (-webkit-foo 1) (--x 1) (colr blue) (zzzzzz 1) (margin 0))").unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let lints=lint_items(&items);
+        assert!(matches!(lints.as_slice(),[
+            Lint::UnknownProperty{name:"colr",suggestion:Some("color")},
+            Lint::UnknownProperty{name:"zzzzzz",suggestion:None},
+        ]),"{:?}",lints);
+        assert_eq!(lints[0].to_string(),"unknown property `colr`. Did you mean `color`?");
+    }
+    fn lowered(source:&str,targets:&str)->String {
+        let file=SFile::parse_file(source).unwrap();
+        let mut items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        lower_items(&mut items,&Targets::parse(targets).unwrap());
+        let mut out=String::new();
+        write_items(&mut out,&items,&RenderOptions{minify:true,..RenderOptions::default()},0).unwrap();
+        out
+    }
+    #[test]
+    fn fallbacks_for_old_targets() {
+        assert_eq!(lowered("(.a (color (oklch 0.7 0.1 200)) (background (lab 50 20 30)))","chrome 100"),
+            ".a{color:#40b1b7;color:oklch(.7 .1 200);background:#a16945;background:lab(50 20 30)}");
+        assert_eq!(lowered("(.a (color (oklch 0.7 0.1 200)))","chrome 120"),".a{color:oklch(.7 .1 200)}");
+        assert_eq!(lowered("(.a (width (clamp 1px 2vw 3px)))","chrome 70"),".a{width:3px;width:max(1px,min(2vw,3px));width:clamp(1px,2vw,3px)}");
+        assert_eq!(lowered("(.a (width (clamp 1px 2vw 3px)))","safari 12"),".a{width:max(1px,min(2vw,3px));width:clamp(1px,2vw,3px)}");
+        assert_eq!(lowered("(.a (margin-inline (1px 2px)) (inset-block 0))","chrome 70"),".a{margin-left:1px;margin-right:2px;margin-inline:1px 2px;top:0;bottom:0;inset-block:0}");
+        assert_eq!(lowered("(.a (margin-inline-start 1px))","chrome 70, chrome 120"),".a{margin-inline-start:1px}");
+    }
+    #[test]
+    fn font_face() {
+        assert_eq!(minified("(@font-face \"My Font\" (url \"a.woff2\" woff2 (tech variations)) (local \"Arial\") (font-weight (100 900)) (font-display swap) (unicode-range U+0000-00FF U+0131))"),
+            "@font-face{font-family:\"My Font\";src:url(\"a.woff2\") format(\"woff2\") tech(variations),local(\"Arial\");font-weight:100 900;font-display:swap;unicode-range:U+0000-00FF,U+0131}");
+        let error=|source|compile(source,&RenderOptions::default()).unwrap_err().err_type;
+        assert!(matches!(error("(@font-face Foo (url \"a.woff\") (font-display sometimes))"),ErrorType::InvalidValue));
+        assert!(matches!(error("(@font-face Foo (url \"a.woff\") (font-colour red))"),ErrorType::UnknownDescriptor));
+    }
+    #[test]
+    fn at_rules() {
+        assert_eq!(minified("(@layer base components) (@layer base (.a (color red))) (@layer (.b (color red)))"),"@layer base,components;@layer base{.a{color:red}}@layer{.b{color:red}}");
+        assert_eq!(minified("(@container sidebar (min-width 400px) (.a (color red)))"),"@container sidebar (min-width:400px){.a{color:red}}");
+        assert_eq!(minified("(@page :first (margin 1in) (@top-center (content \"1\")))"),"@page :first{margin:1in;@top-center{content:\"1\"}}");
+        assert_eq!(minified("(@namespace svg \"http://www.w3.org/2000/svg\")"),"@namespace svg url(\"http://www.w3.org/2000/svg\");");
+        assert_eq!(minified("(@property --x (syntax \"<length>\") (inherits false) (initial-value 0px))"),"@property --x{syntax:\"<length>\";inherits:false;initial-value:0px}");
+        assert_eq!(minified("(@import \"a.css\" (layer x) print)"),"@import \"a.css\" layer(x) print;");
+    }
+    #[test]
+    fn media_queries() {
+        let rule="(.a (color red))";
+        let media=|query:&str|minified(&format!("(@media {} {})",query,rule)).trim_end_matches("{.a{color:red}}").to_string();
+        assert_eq!(media("(only screen)"),"@media only screen");
+        assert_eq!(media("(not print)"),"@media not print");
+        assert_eq!(media("(screen and (min-width 400px))"),"@media screen and (min-width:400px)");
+        assert_eq!(media("((width >= 400px) (orientation landscape))"),"@media (width>=400px),(orientation:landscape)");
+        assert_eq!(media("(400px <= width <= 700px)"),"@media (400px<=width<=700px)");
+        assert_eq!(media("((hover) or (pointer fine))"),"@media (hover) or (pointer:fine)");
+        assert_eq!(media("(screen and ((width >= 1px) or (height >= 1px)))"),"@media screen and ((width>=1px) or (height>=1px))");
+        assert_eq!(pretty("(@media (width >= 400px) (.a (color red)))"),"@media (width >= 400px) {\n    .a {\n        color: red;\n    }\n}\n");
+    }
+    #[test]
+    fn supports_conditions() {
+        let supports=|condition:&str|minified(&format!("(@supports {} (.a (color red)))",condition)).trim_end_matches("{.a{color:red}}").to_string();
+        assert_eq!(supports("(display grid)"),"@supports (display:grid)");
+        assert_eq!(supports("(not (display grid))"),"@supports not (display:grid)");
+        assert_eq!(supports("((display grid) or (display flex))"),"@supports (display:grid) or (display:flex)");
+        assert_eq!(supports("(selector (child a b))"),"@supports selector(a>b)");
+        assert_eq!(supports("(font-tech color-colrv1)"),"@supports font-tech(color-colrv1)");
+        assert_eq!(supports("(font-format woff2)"),"@supports font-format(woff2)");
+        assert_eq!(supports("((not (display grid)) and ((gap 1px) or (selector (:has a))))"),"@supports (not (display:grid)) and ((gap:1px) or selector(:has(a)))");
+    }
+    #[test]
+    fn keyframes() {
+        assert_eq!(minified("(@keyframes fade (from (opacity 0)) (to (opacity 1)))"),"@keyframes fade{0%{opacity:0}to{opacity:1}}");
+        assert_eq!(minified("(@keyframes fade (0% 50% (opacity 0) (animation-timing-function ease-in)) (100% (opacity 1)))"),
+            "@keyframes fade{0%,50%{opacity:0;animation-timing-function:ease-in}100%{opacity:1}}");
+        assert_eq!(minified("(@keyframes spin (to (animation-timing-function (steps 4 jump-end))))"),"@keyframes spin{to{animation-timing-function:steps(4,jump-end)}}");
+        assert!(matches!(compile("(@keyframes fade (150% (opacity 0)))",&RenderOptions::default()).unwrap_err().err_type,ErrorType::PercentOutOfRange));
+    }
+    #[test]
+    fn inlined_imports_keep_layer_and_media() {
+        let file=SFile::parse_file("(@import \"b.cssx\" (layer x) print) (@import \"b.cssx\" layer)").unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let bundled=inline_imports(items,&mut |_|Some(vec![Item::Raw(".b{color:blue}")]));
+        let mut out=String::new();
+        write_items(&mut out,&bundled,&RenderOptions{minify:true,..RenderOptions::default()},0).unwrap();
+        assert_eq!(out,"@media print{@layer x{.b{color:blue}}}@layer{.b{color:blue}}");
+    }
+}
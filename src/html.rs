@@ -1,4 +1,5 @@
 use indexmap::IndexMap;
+use crate::render::RenderOptions;
 use s_expression_parser::{
     Object,
     Location,
@@ -7,12 +8,30 @@ use std::{
     fmt::{
         Write,
         Error as FmtError,
+        Result as FmtResult,
         Display,
         Formatter,
     },
+    io::{
+        Write as IoWrite,
+        Result as IoResult,
+    },
 };
 
 
+const BOOLEAN_ATTRIBUTES:&[&str]=&[
+    "allowfullscreen","async","autofocus","autoplay","checked","controls","default","defer","disabled",
+    "formnovalidate","hidden","inert","ismap","itemscope","loop","multiple","muted","nomodule","novalidate",
+    "open","playsinline","readonly","required","reversed","selected",
+];
+const P_CLOSERS:&[&str]=&[
+    "address","article","aside","blockquote","details","div","dl","fieldset","figcaption","figure","footer",
+    "form","h1","h2","h3","h4","h5","h6","header","hgroup","hr","main","menu","nav","ol","p","pre","section",
+    "table","ul",
+];
+const P_KEEP_END_PARENTS:&[&str]=&["a","audio","del","ins","map","noscript","video"];
+
+
 #[derive(Debug)]
 pub enum ErrorType {
     InvalidAttribute,
@@ -82,84 +101,162 @@ impl<'input> Item<'input> {
         }
         Ok(Self::Tag{name,inner,attributes})
     }
-}
-impl<'input> Display for Item<'input> {
-    fn fmt(&self,f:&mut Formatter)->Result<(),FmtError> {
-        if f.alternate() {  // pretty formatting
-            let indent=f.width().unwrap_or(0);
-            match self {
-                Self::Comment(lines)=>{
-                    match lines.len() {
-                        0=>Ok(()),
-                        1=>{
-                            for _ in 0..indent {
-                                write!(f," ")?;
-                            }
-                            writeln!(f,"<!-- {} -->",lines[0])
-                        },
-                        _=>{
-                            for _ in 0..indent {
-                                write!(f," ")?;
-                            }
-                            writeln!(f,"<!--")?;
-                            for line in lines {
-                                for _ in 0..indent+4 {
-                                    write!(f," ")?;
-                                }
-                                writeln!(f," {}",line)?;
-                            }
-                            for _ in 0..indent {
-                                write!(f," ")?;
-                            }
-                            writeln!(f,"-->")
-                        },
-                    }
+    pub fn write_to<W:IoWrite>(&self,w:&mut W,options:&RenderOptions)->IoResult<()> {
+        write_all(std::slice::from_ref(self),w,options)
+    }
+    fn fmt_attributes<W:Write>(attributes:&IndexMap<&'input str,Option<&'input str>>,f:&mut W)->FmtResult {
+        for (attribute,maybe_data) in attributes {
+            if let Some(data)=maybe_data {
+                write!(f," {}=\"{}\"",attribute,data)?;
+            } else {
+                write!(f," {}",attribute)?;
+            }
+        }
+        Ok(())
+    }
+    fn fmt_pretty<W:Write>(&self,f:&mut W,options:&RenderOptions,depth:usize)->FmtResult {
+        match self {
+            Self::Comment(lines)=>{
+                match lines.len() {
+                    0=>Ok(()),
+                    1=>{
+                        options.write_indent(f,depth)?;
+                        writeln!(f,"<!-- {} -->",lines[0])
+                    },
+                    _=>{
+                        options.write_indent(f,depth)?;
+                        writeln!(f,"<!--")?;
+                        for line in lines {
+                            options.write_indent(f,depth+1)?;
+                            writeln!(f," {}",line)?;
+                        }
+                        options.write_indent(f,depth)?;
+                        writeln!(f,"-->")
+                    },
+                }
+            }
+            Self::Tag{name,attributes,inner}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"<{}",name)?;
+                Self::fmt_attributes(attributes,f)?;
+                f.write_char('>')?;
+                f.write_char('\n')?;
+                for i in inner {
+                    i.fmt_pretty(f,options,depth+1)?;
                 }
+                options.write_indent(f,depth)?;
+                writeln!(f,"</{}>",name)
+            },
+            Self::EmptyTag{name,attributes}=>{
+                options.write_indent(f,depth)?;
+                write!(f,"<{}",name)?;
+                Self::fmt_attributes(attributes,f)?;
+                f.write_char('>')?;
+                f.write_char('\n')
+            },
+            Self::Text(text)=>{
+                options.write_indent(f,depth)?;
+                f.write_str(text)?;
+                f.write_char('\n')
+            },
+        }
+    }
+    fn is_kept_comment(&self,keep_special_comments:bool)->bool {
+        match self {
+            Self::Comment(lines)=>{
+                keep_special_comments&&lines.first()
+                    .map(|line|line.starts_with('!')||line.starts_with("[if"))
+                    .unwrap_or(false)
+            },
+            _=>true,
+        }
+    }
+    fn tag_name(&self)->Option<&'input str> {
+        match self {
+            Self::Tag{name,..}|Self::EmptyTag{name,..}=>Some(name),
+            _=>None,
+        }
+    }
+    fn can_omit_end_tag(name:&str,next:Option<&Self>,parent:Option<&str>)->bool {
+        let next_is=|names:&[&str]|next
+            .and_then(Self::tag_name)
+            .map(|next_name|names.iter().any(|n|n.eq_ignore_ascii_case(next_name)))
+            .unwrap_or(false);
+        match name.to_ascii_lowercase().as_str() {
+            "html"|"body"=>!matches!(next,Some(Self::Comment(_))),
+            "head"=>next.is_none()||next_is(&["body"]),
+            "li"=>next.is_none()||next_is(&["li"]),
+            "dt"=>next_is(&["dt","dd"]),
+            "dd"=>next.is_none()||next_is(&["dt","dd"]),
+            "p"=>{
+                if next.is_none() {
+                    parent
+                        .map(|p|!P_KEEP_END_PARENTS.iter().any(|n|n.eq_ignore_ascii_case(p)))
+                        .unwrap_or(false)
+                } else {
+                    next_is(P_CLOSERS)
+                }
+            },
+            "rt"|"rp"=>next.is_none()||next_is(&["rt","rp"]),
+            "optgroup"=>next.is_none()||next_is(&["optgroup"]),
+            "option"=>next.is_none()||next_is(&["option","optgroup"]),
+            "thead"=>next_is(&["tbody","tfoot"]),
+            "tbody"=>next.is_none()||next_is(&["tbody","tfoot"]),
+            "tfoot"=>next.is_none(),
+            "tr"=>next.is_none()||next_is(&["tr"]),
+            "td"|"th"=>next.is_none()||next_is(&["td","th"]),
+            _=>false,
+        }
+    }
+    fn fmt_minified_attributes(attributes:&IndexMap<&'input str,Option<&'input str>>,f:&mut Formatter)->Result<(),FmtError> {
+        for (attribute,maybe_data) in attributes {
+            match maybe_data {
+                Some(data) if BOOLEAN_ATTRIBUTES.iter().any(|a|a.eq_ignore_ascii_case(attribute))&&(data.is_empty()||data.eq_ignore_ascii_case(attribute))=>{
+                    write!(f," {}",attribute)?;
+                },
+                Some(data) if !data.is_empty()&&!data.contains(|c:char|c.is_ascii_whitespace()||"\"'=<>`".contains(c))=>{
+                    write!(f," {}={}",attribute,data)?;
+                },
+                Some(data)=>write!(f," {}=\"{}\"",attribute,data)?,
+                None=>write!(f," {}",attribute)?,
+            }
+        }
+        Ok(())
+    }
+    fn fmt_minified_siblings(items:&[Self],f:&mut Formatter,keep_special_comments:bool,parent:Option<&str>)->Result<(),FmtError> {
+        let mut kept=items.iter().filter(|i|i.is_kept_comment(keep_special_comments)).peekable();
+        while let Some(item)=kept.next() {
+            let next=kept.peek().copied();
+            match item {
                 Self::Tag{name,attributes,inner}=>{
-                    for _ in 0..indent {
-                        write!(f," ")?;
-                    }
                     write!(f,"<{}",name)?;
-                    for (attribute,maybe_data) in attributes {
-                        if let Some(data)=maybe_data {
-                            write!(f," {}=\"{}\"",attribute,data)?;
-                        } else {
-                            write!(f," {}",attribute)?;
-                        }
-                    }
+                    Self::fmt_minified_attributes(attributes,f)?;
                     f.write_char('>')?;
-                    f.write_char('\n')?;
-                    for i in inner {
-                        write!(f,"{:#1$}",i,indent+4)?;
-                    }
-                    for _ in 0..indent {
-                        write!(f," ")?;
+                    Self::fmt_minified_siblings(inner,f,keep_special_comments,Some(name))?;
+                    if !Self::can_omit_end_tag(name,next,parent) {
+                        write!(f,"</{}>",name)?;
                     }
-                    writeln!(f,"</{}>",name)
                 },
                 Self::EmptyTag{name,attributes}=>{
-                    for _ in 0..indent {
-                        write!(f," ")?;
-                    }
                     write!(f,"<{}",name)?;
-                    for (attribute,maybe_data) in attributes {
-                        if let Some(data)=maybe_data {
-                            write!(f," {}=\"{}\"",attribute,data)?;
-                        } else {
-                            write!(f," {}",attribute)?;
-                        }
-                    }
+                    Self::fmt_minified_attributes(attributes,f)?;
                     f.write_char('>')?;
-                    f.write_char('\n')
                 },
-                Self::Text(text)=>{
-                    for _ in 0..indent {
-                        write!(f," ")?;
-                    }
-                    f.write_str(&text)?;
-                    f.write_char('\n')
+                // IE only recognizes `<!--[if ...]>` and `<![endif]-->` without spaces
+                Self::Comment(lines) if lines.first().is_some_and(|line|line.starts_with("[if"))=>{
+                    write!(f,"<!--{}-->",lines.join(" "))?;
                 },
+                Self::Text(_)|Self::Comment(_)=>item.fmt(f)?,
             }
+        }
+        Ok(())
+    }
+}
+impl<'input> Display for Item<'input> {
+    fn fmt(&self,f:&mut Formatter)->Result<(),FmtError> {
+        if f.alternate() {  // pretty formatting
+            let depth=f.width().unwrap_or(0);
+            self.fmt_pretty(f,&RenderOptions::default(),depth)
         } else {    // minimized formatting
             match self {
                 Self::Comment(lines)=>{
@@ -179,13 +276,7 @@ impl<'input> Display for Item<'input> {
                 },
                 Self::Tag{name,attributes,inner}=>{
                     write!(f,"<{}",name)?;
-                    for (attribute,maybe_data) in attributes {
-                        if let Some(data)=maybe_data {
-                            write!(f," {}=\"{}\"",attribute,data)?;
-                        } else {
-                            write!(f," {}",attribute)?;
-                        }
-                    }
+                    Self::fmt_attributes(attributes,f)?;
                     f.write_char('>')?;
                     for i in inner {
                         i.fmt(f)?;
@@ -194,13 +285,7 @@ impl<'input> Display for Item<'input> {
                 },
                 Self::EmptyTag{name,attributes}=>{
                     write!(f,"<{}",name)?;
-                    for (attribute,maybe_data) in attributes {
-                        if let Some(data)=maybe_data {
-                            write!(f," {}=\"{}\"",attribute,data)?;
-                        } else {
-                            write!(f," {}",attribute)?;
-                        }
-                    }
+                    Self::fmt_attributes(attributes,f)?;
                     f.write_char('>')
                 },
                 Self::Text(text)=>f.write_str(&text),
@@ -244,9 +329,84 @@ impl<'input> TryFrom<&'input Object<'input>> for Item<'input> {
 }
 
 
+struct Rendered<'a,'input> {
+    items:&'a [Item<'input>],
+    options:&'a RenderOptions,
+}
+impl<'a,'input> Display for Rendered<'a,'input> {
+    fn fmt(&self,f:&mut Formatter)->Result<(),FmtError> {
+        if self.options.minify {
+            return Item::fmt_minified_siblings(self.items,f,self.options.keep_special_comments,None);
+        }
+        for i in self.items {
+            if self.options.pretty {
+                i.fmt_pretty(f,self.options,0)?;
+            } else {
+                i.fmt(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+pub fn write_all<W:IoWrite>(items:&[Item],w:&mut W,options:&RenderOptions)->IoResult<()> {
+    write!(w,"{}",Rendered{items,options})
+}
+
+
 #[derive(Debug)]
 pub struct Error {
     pub start:Location,
     pub end:Location,
     pub err_type:ErrorType,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use s_expression_parser::File as SFile;
+    use super::*;
+    fn minified(source:&str,keep_special_comments:bool)->String {
+        let file=SFile::parse_file(source).unwrap();
+        let items=file.items.iter().map(Item::try_from).collect::<Result<Vec<_>,_>>().unwrap();
+        let mut out=Vec::new();
+        write_all(&items,&mut out,&RenderOptions{minify:true,keep_special_comments,..RenderOptions::default()}).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn write_to_matches_display() {
+        let file=SFile::parse_file("(div (p \"a\") ((a (href \"/x\")) \"b\"))").unwrap();
+        let item=Item::try_from(&file.items[0]).unwrap();
+        let mut out=Vec::new();
+        item.write_to(&mut out,&RenderOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),item.to_string());
+        let mut out=Vec::new();
+        item.write_to(&mut out,&RenderOptions{pretty:true,..RenderOptions::default()}).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),format!("{:#}",item));
+    }
+    #[test]
+    fn p_end_tag_is_omitted_before_blocks() {
+        assert_eq!(minified("(div (p \"a\") (ul (li \"b\")) (p \"c\") (table) (p \"d\"))",false),"<div><p>a<ul><li>b</ul><p>c<table></table><p>d</div>");
+        assert_eq!(minified("(div (p \"a\") (span \"b\"))",false),"<div><p>a</p><span>b</span></div>");
+        assert_eq!(minified("(a (p \"a\"))",false),"<a><p>a</p></a>");
+    }
+    #[test]
+    fn li_and_td_end_tags_are_omitted() {
+        assert_eq!(minified("(ul (li \"a\") (li \"b\"))",false),"<ul><li>a<li>b</ul>");
+        assert_eq!(minified("(table (tr (td \"a\") (th \"b\")) (tr (td \"c\")))",false),"<table><tr><td>a<th>b<tr><td>c</table>");
+        assert_eq!(minified("(ul (li \"a\") (// \"x\") (div))",false),"<ul><li>a</li><div></div></ul>");
+    }
+    #[test]
+    fn attributes_are_only_unquoted_when_safe() {
+        assert_eq!(minified("((a (href \"/x\") (title \"a=b\") (data-x \"a`b\") (alt \"a b\") (value \"\")) \"y\")",false),
+            "<a href=/x title=\"a=b\" data-x=\"a`b\" alt=\"a b\" value=\"\">y</a>");
+    }
+    #[test]
+    fn special_comments_are_kept() {
+        let source="(div (// \"[if IE]><p>old</p><![endif]\") (// \"! license\") (// \"gone\"))";
+        assert_eq!(minified(source,true),"<div><!--[if IE]><p>old</p><![endif]--><!-- ! license --></div>");
+        assert_eq!(minified(source,false),"<div></div>");
+    }
+}
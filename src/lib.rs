@@ -0,0 +1,4 @@
+pub mod html;
+pub mod css;
+pub mod render;
+pub mod targets;
@@ -6,24 +6,50 @@ use clap::{
     Subcommand,
 };
 use std::{
-    fmt::Write,
+    io::{
+        Write as IoWrite,
+        BufWriter,
+    },
     fs::{
+        File,
         read_to_string,
         write as write_file,
     },
+    path::{
+        Path,
+        PathBuf,
+        Component,
+    },
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    process::exit,
 };
-use html::{
-    Item as HtmlItem,
-};
-use css::{
-    Item as CssItem,
+use htsx::{
+    html::{
+        Item as HtmlItem,
+        write_all as write_html,
+    },
+    css::{
+        Item as CssItem,
+        Constants,
+        CustomProperties,
+        lint_items,
+        prefix_items,
+        lower_items,
+        inline_imports,
+        raw_css_items,
+        write_items as write_css,
+    },
+    render::{
+        RenderOptions,
+        Indent,
+    },
+    targets::Targets,
 };
 
 
-mod html;
-mod css;
-
-
 #[derive(Subcommand,Debug)]
 pub enum SubCommand {
     Lsp,
@@ -31,6 +57,30 @@ pub enum SubCommand {
         #[clap(parse(from_flag),short,long)]
         #[clap(help="Generate human-readable HTML code")]
         pretty:bool,
+        #[clap(parse(from_flag),short,long,conflicts_with="pretty")]
+        #[clap(help="Generate the smallest HTML or CSS that still produces the same result")]
+        minify:bool,
+        #[clap(parse(from_flag),long,requires="minify")]
+        #[clap(help="Keep conditional and license (`!`) comments when minifying HTML")]
+        keep_special_comments:bool,
+        #[clap(long,default_value="4")]
+        #[clap(help="Number of spaces per indentation level")]
+        indent:usize,
+        #[clap(parse(from_flag),long)]
+        #[clap(help="Indent with tabs instead of spaces")]
+        tabs:bool,
+        #[clap(parse(from_flag),long)]
+        #[clap(help="Put an empty line between CSS rules")]
+        blank_lines:bool,
+        #[clap(parse(from_flag),long)]
+        #[clap(help="Write each CSS rule on a single line")]
+        compact_rules:bool,
+        #[clap(long,parse(try_from_str=Targets::parse))]
+        #[clap(help="Browsers the CSS has to work in, like `last 2 versions`. Adds and removes vendor prefixes to match, and adds fallbacks for newer syntax. Logical property fallbacks assume left-to-right text. Prefixed values like `-webkit-box` aren't added.")]
+        targets:Option<Targets>,
+        #[clap(parse(from_flag),long)]
+        #[clap(help="Inline `@import`s of local .cssx and .css files so each stylesheet is a single file")]
+        bundle:bool,
         names:Vec<String>,
     },
 }
@@ -47,11 +97,25 @@ fn main() {
     let args=Command::parse();
     use SubCommand as SC;
     match args.subcommand {
-        SC::Convert{names,pretty}=>names.into_iter().for_each(|name|parse_and_write(&name,pretty)),
+        SC::Convert{names,pretty,minify,keep_special_comments,indent,tabs,blank_lines,compact_rules,targets,bundle}=>{
+            let options=RenderOptions {
+                pretty,
+                minify,
+                keep_special_comments,
+                indent:if tabs {Indent::Tabs} else {Indent::Spaces(indent)},
+                blank_lines,
+                compact_rules,
+            };
+            let mut custom_properties=CustomProperties::default();
+            names.into_iter().for_each(|name|parse_and_write(&name,&options,targets.as_ref(),bundle,&mut custom_properties));
+            for (name,source) in custom_properties.undeclared() {
+                eprintln!("Warning: custom property `{}` is used in {} but never declared",name,source);
+            }
+        },
         SC::Lsp=>todo!("LSP client"),
     }
 }
-fn parse_and_write(name:&str,pretty:bool) {
+fn parse_and_write(name:&str,options:&RenderOptions,targets:Option<&Targets>,bundle:bool,custom_properties:&mut CustomProperties) {
     if name.ends_with(".htsx") {
         let contents=read_to_string(&name).unwrap();
         let file=SFile::parse_file(&contents).unwrap();
@@ -59,29 +123,169 @@ fn parse_and_write(name:&str,pretty:bool) {
         for i in file.items.iter() {
             elements.push(i.try_into().unwrap());
         }
-        let mut out=String::from("<!-- HTML Generated with HTSX: github.com/Clinery1/htsx -->\n");
-        if pretty {
-            for i in elements.iter() {
-                write!(out,"{:#}",i).unwrap();
-            }
-        } else {
-            for i in elements.iter() {
-                write!(out,"{}",i).unwrap();
+        let mut out=BufWriter::new(File::create(format!("{}ml",&name[..name.len()-2])).unwrap());
+        writeln!(out,"<!-- HTML Generated with HTSX: github.com/Clinery1/htsx -->").unwrap();
+        write_html(&elements,&mut out,options).unwrap();
+        out.flush().unwrap();
+    } else if name.ends_with(".cssx") {
+        let path=normalize_path(Path::new(name));
+        let main_source=read_source(path.clone(),read_to_string(name).unwrap());
+        let mut sources=Vec::new();
+        if bundle {
+            if let Err(cycle)=read_imports(&path,&main_source.contents,&mut vec![path.clone()],&mut sources) {
+                eprintln!("Error: {} imports itself: {}",name,cycle.join(" -> "));
+                exit(1);
             }
         }
-        write_file(format!("{}ml",&name[..name.len()-2]),out).unwrap();
-    } else if name.ends_with(".cssx") {
-        let contents=read_to_string(&name).unwrap();
-        let file=SFile::parse_file(&contents).unwrap();
-        let mut elements:Vec<CssItem>=Vec::new();
-        for i in file.items.iter() {
-            elements.push(i.try_into().unwrap());
+        sources.push(main_source);
+        let files=sources.iter()
+            .map(|source|source.path.ends_with(".cssx").then(||SFile::parse_file(&source.contents).unwrap()))
+            .collect::<Vec<_>>();
+        let used_files=sources.iter()
+            .map(|source|source.uses.iter().map(|used|SFile::parse_file(used).unwrap()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        // every file is compiled with its own constants before it gets inlined
+        let mut compiled=HashMap::new();
+        for ((source,file),used_files) in sources.iter().zip(files.iter()).zip(used_files.iter()) {
+            let elements=match file {
+                Some(file)=>compile_css(file,used_files),
+                None=>raw_css_items(&source.contents).unwrap_or_else(|import|{
+                    eprintln!("Error: {} imports {}, but local imports in plain .css files can't be bundled",source.path,import);
+                    exit(1);
+                }),
+            };
+            compiled.insert(source.path.as_str(),elements);
+        }
+        let mut elements=compiled.remove(path.as_str()).unwrap();
+        if bundle {
+            elements=bundle_imports(&path,elements,&compiled,&mut HashSet::from([path.clone()]));
+        }
+        if let Some(targets)=targets {
+            lower_items(&mut elements,targets);
+            prefix_items(&mut elements,targets);
         }
         let mut out=String::from("/* CSS Generated with HTSX: github.com/Clinery1/htsx */\n");
-        for i in elements.iter() {
-            // dbg!(i);
-            i.into_css(&mut out,0).unwrap();
+        custom_properties.add_items(&elements,name);
+        for lint in lint_items(&elements) {
+            eprintln!("Warning: {} ({})",lint,name);
         }
+        write_css(&mut out,&elements,options,0).unwrap();
         write_file(format!("{}",&name[..name.len()-1]),out).unwrap();
     }
 }
+fn compile_css<'a>(file:&'a SFile<'a>,used_files:&'a [SFile<'a>])->Vec<CssItem<'a>> {
+    let mut elements:Vec<CssItem>=Vec::new();
+    for i in file.items.iter() {
+        elements.push(i.try_into().unwrap());
+    }
+    let mut constants=Constants::default();
+    for used in used_files.iter() {
+        let mut used_elements:Vec<CssItem>=Vec::new();
+        for i in used.items.iter() {
+            used_elements.push(i.try_into().unwrap());
+        }
+        constants.add_items(&used_elements).unwrap();
+    }
+    constants.add_items(&elements).unwrap();
+    constants.resolve_items(&mut elements).unwrap();
+    elements
+}
+// each file is only inlined the first time it is imported
+fn bundle_imports<'a>(name:&str,elements:Vec<CssItem<'a>>,compiled:&HashMap<&str,Vec<CssItem<'a>>>,inlined:&mut HashSet<String>)->Vec<CssItem<'a>> {
+    inline_imports(elements,&mut |path|{
+        let path=relative_path(name,path);
+        let elements=compiled.get(path.as_str())?;
+        if !inlined.insert(path.clone()) {
+            return Some(Vec::new());
+        }
+        Some(bundle_imports(&path,elements.clone(),compiled,inlined))
+    })
+}
+struct Source {
+    path:String,
+    contents:String,
+    uses:Vec<String>,
+}
+fn read_source(path:String,contents:String)->Source {
+    let mut uses=Vec::new();
+    if path.ends_with(".cssx") {
+        read_uses(&path,&contents,&mut vec![path.clone()],&mut uses);
+    }
+    Source{path,contents,uses}
+}
+fn read_imports(name:&str,contents:&str,stack:&mut Vec<String>,sources:&mut Vec<Source>)->Result<(),Vec<String>> {
+    let file=SFile::parse_file(contents).unwrap();
+    for i in file.items.iter() {
+        let Some(path)=CssItem::try_from(i).ok().and_then(|item|item.local_import()) else {
+            continue;
+        };
+        let path=relative_path(name,path);
+        if stack.contains(&path) {
+            let mut cycle=stack.clone();
+            cycle.push(path);
+            return Err(cycle);
+        }
+        if sources.iter().any(|source|source.path==path) {
+            continue;
+        }
+        let source=read_source(path.clone(),read_dependency(name,"imports",&path));
+        if path.ends_with(".cssx") {
+            stack.push(path.clone());
+            read_imports(&path,&source.contents,stack,sources)?;
+            stack.pop();
+        }
+        sources.push(source);
+    }
+    Ok(())
+}
+fn read_dependency(name:&str,how:&str,path:&str)->String {
+    read_to_string(path).unwrap_or_else(|_|{
+        eprintln!("Error: {} {} {}, which could not be read",name,how,path);
+        exit(1);
+    })
+}
+fn relative_path(name:&str,path:&str)->String {
+    normalize_path(&Path::new(name).parent().unwrap_or(Path::new("")).join(path))
+}
+fn normalize_path(path:&Path)->String {
+    let mut normalized=PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir=>{},
+            Component::ParentDir if matches!(normalized.components().next_back(),Some(Component::Normal(_)))=>{
+                normalized.pop();
+            },
+            _=>normalized.push(component),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
+fn read_uses(name:&str,contents:&str,seen:&mut Vec<String>,sources:&mut Vec<String>) {
+    let file=SFile::parse_file(contents).unwrap();
+    for i in file.items.iter() {
+        if let Ok(CssItem::Use(path))=CssItem::try_from(i) {
+            let path=relative_path(name,path);
+            if seen.contains(&path) {
+                continue;
+            }
+            let used_contents=read_dependency(name,"uses",&path);
+            seen.push(path.clone());
+            read_uses(&path,&used_contents,seen,sources);
+            sources.push(used_contents);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_paths() {
+        assert_eq!(relative_path("styles/main.cssx","./parts/../base.cssx"),"styles/base.cssx");
+        assert_eq!(relative_path("main.cssx","../shared/a.css"),"../shared/a.css");
+        assert_eq!(relative_path("a/b/main.cssx","../../c.css"),"c.css");
+        assert_eq!(normalize_path(Path::new("../a/./../b")),"../b");
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    fmt::{
+        Result as FmtResult,
+        Write,
+    },
+};
+
+
+#[derive(Debug,Clone,Copy)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+
+#[derive(Debug,Clone)]
+pub struct RenderOptions {
+    pub pretty:bool,
+    pub minify:bool,
+    pub keep_special_comments:bool,
+    pub indent:Indent,
+    pub blank_lines:bool,
+    pub compact_rules:bool,
+}
+impl RenderOptions {
+    pub fn write_indent<W:Write>(&self,f:&mut W,depth:usize)->FmtResult {
+        match self.indent {
+            Indent::Spaces(width)=>{
+                for _ in 0..depth*width {
+                    f.write_char(' ')?;
+                }
+            },
+            Indent::Tabs=>{
+                for _ in 0..depth {
+                    f.write_char('\t')?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+impl Default for RenderOptions {
+    fn default()->Self {
+        RenderOptions {
+            pretty:false,
+            minify:false,
+            keep_special_comments:false,
+            indent:Indent::Spaces(4),
+            blank_lines:false,
+            compact_rules:false,
+        }
+    }
+}
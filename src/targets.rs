@@ -0,0 +1,343 @@
+use std::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Firefox,
+    Safari,
+    IosSafari,
+    Opera,
+    Samsung,
+}
+impl Browser {
+    const ALL:&'static [Browser]=&[
+        Browser::Chrome,
+        Browser::Edge,
+        Browser::Firefox,
+        Browser::Safari,
+        Browser::IosSafari,
+        Browser::Opera,
+        Browser::Samsung,
+    ];
+    pub fn from_name(name:&str)->Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chrome"|"and_chr"|"chromeandroid"=>Some(Self::Chrome),
+            "edge"=>Some(Self::Edge),
+            "firefox"|"ff"|"and_ff"|"firefoxandroid"=>Some(Self::Firefox),
+            "safari"=>Some(Self::Safari),
+            "ios_saf"|"ios"=>Some(Self::IosSafari),
+            "opera"=>Some(Self::Opera),
+            "samsung"=>Some(Self::Samsung),
+            _=>None,
+        }
+    }
+    fn versions(self)->Vec<Version> {
+        let majors=|range:std::ops::RangeInclusive<u32>|range.map(|major|Version(major,0)).collect::<Vec<_>>();
+        match self {
+            Self::Chrome=>majors(4..=141),
+            Self::Edge=>majors(12..=18).into_iter().chain(majors(79..=141)).collect(),
+            Self::Firefox=>majors(2..=144),
+            Self::Safari|Self::IosSafari=>SAFARI_VERSIONS.iter().map(|(major,minor)|Version(*major,*minor)).collect(),
+            Self::Opera=>majors(9..=122),
+            Self::Samsung=>majors(4..=28),
+        }
+    }
+}
+impl Display for Browser {
+    fn fmt(&self,f:&mut Formatter)->FmtResult {
+        f.write_str(match self {
+            Self::Chrome=>"chrome",
+            Self::Edge=>"edge",
+            Self::Firefox=>"firefox",
+            Self::Safari=>"safari",
+            Self::IosSafari=>"ios_saf",
+            Self::Opera=>"opera",
+            Self::Samsung=>"samsung",
+        })
+    }
+}
+
+
+const SAFARI_VERSIONS:&[(u32,u32)]=&[
+    (3,1),(3,2),(4,0),(5,0),(5,1),(6,0),(6,1),(7,0),(7,1),(8,0),(9,0),(9,1),(10,0),(10,1),(11,0),(11,1),(12,0),(12,1),
+    (13,0),(13,1),(14,0),(14,1),(15,0),(15,1),(15,2),(15,4),(15,5),(15,6),(16,0),(16,1),(16,2),(16,3),(16,4),(16,5),
+    (16,6),(17,0),(17,1),(17,2),(17,3),(17,4),(17,5),(17,6),(18,0),(18,1),(18,2),(18,3),(18,4),(18,5),(26,0),
+];
+
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub struct Version(pub u32,pub u32);
+impl Version {
+    pub const NEVER:Version=Version(u32::MAX,0);
+    fn parse(text:&str)->Option<Self> {
+        let (major,minor)=text.split_once('.').unwrap_or((text,"0"));
+        Some(Version(major.parse().ok()?,minor.parse().ok()?))
+    }
+}
+
+
+pub(crate) type Support=&'static [(Browser,Version)];
+pub(crate) const PREFIXED_PROPERTIES:&[(&str,&str,Support)]=&[
+    ("user-select","-webkit-user-select",&[
+        (Browser::Chrome,Version(54,0)),(Browser::Edge,Version(79,0)),(Browser::Opera,Version(41,0)),
+        (Browser::Samsung,Version(6,0)),(Browser::Safari,Version::NEVER),(Browser::IosSafari,Version::NEVER),
+    ]),
+    ("user-select","-moz-user-select",&[(Browser::Firefox,Version(69,0))]),
+    ("appearance","-webkit-appearance",&[
+        (Browser::Chrome,Version(84,0)),(Browser::Edge,Version(84,0)),(Browser::Opera,Version(70,0)),
+        (Browser::Samsung,Version(14,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("appearance","-moz-appearance",&[(Browser::Firefox,Version(80,0))]),
+    ("backdrop-filter","-webkit-backdrop-filter",&[(Browser::Safari,Version(18,0)),(Browser::IosSafari,Version(18,0))]),
+    ("text-size-adjust","-webkit-text-size-adjust",&[(Browser::Safari,Version::NEVER),(Browser::IosSafari,Version::NEVER)]),
+    ("hyphens","-webkit-hyphens",&[(Browser::Safari,Version(17,0)),(Browser::IosSafari,Version(17,0))]),
+    ("hyphens","-moz-hyphens",&[(Browser::Firefox,Version(43,0))]),
+    ("tab-size","-moz-tab-size",&[(Browser::Firefox,Version(91,0))]),
+    ("box-decoration-break","-webkit-box-decoration-break",&[
+        (Browser::Chrome,Version(130,0)),(Browser::Edge,Version(130,0)),(Browser::Opera,Version(115,0)),
+        (Browser::Samsung,Version::NEVER),(Browser::Safari,Version::NEVER),(Browser::IosSafari,Version::NEVER),
+    ]),
+    ("print-color-adjust","-webkit-print-color-adjust",&[
+        (Browser::Chrome,Version(136,0)),(Browser::Edge,Version(136,0)),(Browser::Opera,Version(121,0)),
+        (Browser::Samsung,Version::NEVER),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("initial-letter","-webkit-initial-letter",&[(Browser::Safari,Version::NEVER),(Browser::IosSafari,Version::NEVER)]),
+    ("text-emphasis","-webkit-text-emphasis",&[
+        (Browser::Chrome,Version(99,0)),(Browser::Edge,Version(99,0)),(Browser::Opera,Version(85,0)),(Browser::Samsung,Version(18,0)),
+    ]),
+    ("text-emphasis-color","-webkit-text-emphasis-color",&[
+        (Browser::Chrome,Version(99,0)),(Browser::Edge,Version(99,0)),(Browser::Opera,Version(85,0)),(Browser::Samsung,Version(18,0)),
+    ]),
+    ("text-emphasis-style","-webkit-text-emphasis-style",&[
+        (Browser::Chrome,Version(99,0)),(Browser::Edge,Version(99,0)),(Browser::Opera,Version(85,0)),(Browser::Samsung,Version(18,0)),
+    ]),
+    ("clip-path","-webkit-clip-path",&[
+        (Browser::Chrome,Version(55,0)),(Browser::Opera,Version(42,0)),(Browser::Samsung,Version(6,0)),
+        (Browser::Safari,Version(13,1)),(Browser::IosSafari,Version(13,0)),
+    ]),
+    ("mask","-webkit-mask",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("mask-image","-webkit-mask-image",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("mask-size","-webkit-mask-size",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("mask-position","-webkit-mask-position",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("mask-repeat","-webkit-mask-repeat",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4)),
+    ]),
+    ("background-clip","-webkit-background-clip",&[
+        (Browser::Chrome,Version(120,0)),(Browser::Edge,Version(120,0)),(Browser::Opera,Version(106,0)),
+        (Browser::Samsung,Version(25,0)),(Browser::Safari,Version(14,0)),(Browser::IosSafari,Version(14,0)),
+    ]),
+    // only kept around so manually prefixed ones can be dropped
+    ("border-radius","-webkit-border-radius",&[(Browser::Chrome,Version(5,0)),(Browser::Safari,Version(5,0)),(Browser::IosSafari,Version(4,0))]),
+    ("border-radius","-moz-border-radius",&[(Browser::Firefox,Version(4,0))]),
+    ("box-shadow","-webkit-box-shadow",&[(Browser::Chrome,Version(10,0)),(Browser::Safari,Version(5,1)),(Browser::IosSafari,Version(5,0))]),
+    ("box-shadow","-moz-box-shadow",&[(Browser::Firefox,Version(4,0))]),
+    ("box-sizing","-webkit-box-sizing",&[(Browser::Chrome,Version(10,0)),(Browser::Safari,Version(5,1)),(Browser::IosSafari,Version(5,0))]),
+    ("box-sizing","-moz-box-sizing",&[(Browser::Firefox,Version(29,0))]),
+    ("transition","-webkit-transition",&[(Browser::Chrome,Version(26,0)),(Browser::Safari,Version(7,0)),(Browser::IosSafari,Version(7,0))]),
+    ("transition","-moz-transition",&[(Browser::Firefox,Version(16,0))]),
+    ("transform","-webkit-transform",&[(Browser::Chrome,Version(36,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("transform","-moz-transform",&[(Browser::Firefox,Version(16,0))]),
+    ("transform","-ms-transform",&[(Browser::Edge,Version(12,0))]),
+    ("transform-origin","-webkit-transform-origin",&[(Browser::Chrome,Version(36,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("animation","-webkit-animation",&[(Browser::Chrome,Version(43,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("animation","-moz-animation",&[(Browser::Firefox,Version(16,0))]),
+    ("flex","-webkit-flex",&[(Browser::Chrome,Version(29,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("flex-direction","-webkit-flex-direction",&[(Browser::Chrome,Version(29,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("flex-wrap","-webkit-flex-wrap",&[(Browser::Chrome,Version(29,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("justify-content","-webkit-justify-content",&[(Browser::Chrome,Version(29,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("align-items","-webkit-align-items",&[(Browser::Chrome,Version(29,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("columns","-webkit-columns",&[(Browser::Chrome,Version(50,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0))]),
+    ("columns","-moz-columns",&[(Browser::Firefox,Version(52,0))]),
+    ("filter","-webkit-filter",&[(Browser::Chrome,Version(53,0)),(Browser::Safari,Version(9,1)),(Browser::IosSafari,Version(9,3))]),
+];
+pub(crate) const PREFIXED_PSEUDOS:&[(&str,&str,Support)]=&[
+    ("::selection","::-moz-selection",&[(Browser::Firefox,Version(62,0))]),
+    ("::placeholder","::-webkit-input-placeholder",&[
+        (Browser::Chrome,Version(57,0)),(Browser::Edge,Version(79,0)),(Browser::Opera,Version(44,0)),
+        (Browser::Samsung,Version(7,0)),(Browser::Safari,Version(10,1)),(Browser::IosSafari,Version(10,3)),
+    ]),
+    ("::placeholder","::-moz-placeholder",&[(Browser::Firefox,Version(51,0))]),
+    ("::file-selector-button","::-webkit-file-upload-button",&[
+        (Browser::Chrome,Version(89,0)),(Browser::Edge,Version(89,0)),(Browser::Opera,Version(75,0)),
+        (Browser::Samsung,Version(15,0)),(Browser::Safari,Version(14,1)),(Browser::IosSafari,Version(14,5)),
+    ]),
+    ("::backdrop","::-webkit-backdrop",&[(Browser::Safari,Version(15,4)),(Browser::IosSafari,Version(15,4))]),
+    (":fullscreen",":-webkit-full-screen",&[
+        (Browser::Chrome,Version(71,0)),(Browser::Edge,Version(79,0)),(Browser::Opera,Version(58,0)),
+        (Browser::Samsung,Version(10,0)),(Browser::Safari,Version(16,4)),
+    ]),
+    (":fullscreen",":-moz-full-screen",&[(Browser::Firefox,Version(64,0))]),
+    (":autofill",":-webkit-autofill",&[
+        (Browser::Chrome,Version(110,0)),(Browser::Edge,Version(110,0)),(Browser::Opera,Version(96,0)),
+        (Browser::Samsung,Version(21,0)),(Browser::Safari,Version(15,0)),(Browser::IosSafari,Version(15,0)),
+    ]),
+    (":any-link",":-webkit-any-link",&[
+        (Browser::Chrome,Version(65,0)),(Browser::Edge,Version(79,0)),(Browser::Opera,Version(52,0)),
+        (Browser::Samsung,Version(9,0)),(Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0)),
+    ]),
+    (":read-only",":-moz-read-only",&[(Browser::Firefox,Version(78,0))]),
+    (":read-write",":-moz-read-write",&[(Browser::Firefox,Version(78,0))]),
+];
+pub(crate) const PREFIXED_KEYFRAMES:&[(&str,Support)]=&[
+    ("-webkit-",&[
+        (Browser::Chrome,Version(43,0)),(Browser::Opera,Version(30,0)),(Browser::Samsung,Version(4,0)),
+        (Browser::Safari,Version(9,0)),(Browser::IosSafari,Version(9,0)),
+    ]),
+    ("-moz-",&[(Browser::Firefox,Version(16,0))]),
+];
+
+pub(crate) const LAB_COLORS:Support=&[
+    (Browser::Chrome,Version(111,0)),(Browser::Edge,Version(111,0)),(Browser::Firefox,Version(113,0)),
+    (Browser::Opera,Version(97,0)),(Browser::Samsung,Version(22,0)),(Browser::Safari,Version(15,4)),
+    (Browser::IosSafari,Version(15,4)),
+];
+pub(crate) const LOGICAL_PROPERTIES:Support=&[
+    (Browser::Chrome,Version(87,0)),(Browser::Edge,Version(87,0)),(Browser::Firefox,Version(66,0)),
+    (Browser::Opera,Version(73,0)),(Browser::Samsung,Version(14,0)),(Browser::Safari,Version(14,1)),
+    (Browser::IosSafari,Version(14,5)),
+];
+pub(crate) const CLAMP:Support=&[
+    (Browser::Chrome,Version(79,0)),(Browser::Edge,Version(79,0)),(Browser::Firefox,Version(75,0)),
+    (Browser::Opera,Version(66,0)),(Browser::Samsung,Version(12,0)),(Browser::Safari,Version(13,1)),
+    (Browser::IosSafari,Version(13,4)),
+];
+pub(crate) const MIN_MAX:Support=&[
+    (Browser::Chrome,Version(79,0)),(Browser::Edge,Version(79,0)),(Browser::Firefox,Version(75,0)),
+    (Browser::Opera,Version(66,0)),(Browser::Samsung,Version(12,0)),(Browser::Safari,Version(11,1)),
+    (Browser::IosSafari,Version(11,3)),
+];
+
+
+#[derive(Debug)]
+pub enum TargetsError {
+    UnknownBrowser(String),
+    UnsupportedQuery(String),
+    InvalidQuery(String),
+}
+impl Display for TargetsError {
+    fn fmt(&self,f:&mut Formatter)->FmtResult {
+        match self {
+            Self::UnknownBrowser(name)=>write!(f,"unknown browser `{}`",name),
+            Self::UnsupportedQuery(query)=>write!(f,"`{}` isn't supported without usage data",query),
+            Self::InvalidQuery(query)=>write!(f,"invalid target query `{}`",query),
+        }
+    }
+}
+impl std::error::Error for TargetsError {}
+
+
+#[derive(Debug,Clone,Default)]
+pub struct Targets {
+    browsers:Vec<(Browser,Version)>,
+}
+impl Targets {
+    pub fn parse(query:&str)->Result<Self,TargetsError> {
+        let mut targets=Targets::default();
+        for part in query.split(',').flat_map(|part|part.split(" or ")) {
+            let words=part.split_whitespace().collect::<Vec<_>>();
+            let invalid=||TargetsError::InvalidQuery(part.trim().to_string());
+            let browser=|name:&str|Browser::from_name(name).ok_or_else(||TargetsError::UnknownBrowser(name.to_string()));
+            match words.as_slice() {
+                []=>{},
+                ["defaults"]=>Browser::ALL.iter().for_each(|b|targets.add_last(*b,2)),
+                ["last",count,"versions"|"version"]=>{
+                    let count=count.parse().map_err(|_|invalid())?;
+                    Browser::ALL.iter().for_each(|b|targets.add_last(*b,count));
+                },
+                ["last",count,name,"versions"|"version"]=>{
+                    let count=count.parse().map_err(|_|invalid())?;
+                    targets.add_last(browser(name)?,count);
+                },
+                [name,op@(">="|">"),version]=>{
+                    let browser=browser(name)?;
+                    let version=Version::parse(version).ok_or_else(invalid)?;
+                    for v in browser.versions() {
+                        if v>version||(*op==">="&&v==version) {
+                            targets.add(browser,v);
+                        }
+                    }
+                },
+                [first,..] if first.starts_with('>')||first.starts_with('<')=>{
+                    return Err(TargetsError::UnsupportedQuery(part.trim().to_string()));
+                },
+                [name,version]=>{
+                    let browser=browser(name)?;
+                    targets.add(browser,Version::parse(version).ok_or_else(invalid)?);
+                },
+                _=>return Err(invalid()),
+            }
+        }
+        Ok(targets)
+    }
+    fn add(&mut self,browser:Browser,version:Version) {
+        if !self.browsers.contains(&(browser,version)) {
+            self.browsers.push((browser,version));
+        }
+    }
+    fn add_last(&mut self,browser:Browser,count:usize) {
+        let versions=browser.versions();
+        for version in versions.iter().rev().take(count) {
+            self.add(browser,*version);
+        }
+    }
+    pub fn is_empty(&self)->bool {
+        self.browsers.is_empty()
+    }
+    pub fn needs(&self,supported_since:&[(Browser,Version)])->bool {
+        self.browsers.iter().any(|(browser,version)|{
+            supported_since.iter().any(|(b,since)|b==browser&&version<since)
+        })
+    }
+    pub fn needs_everywhere(&self,supported_since:&[(Browser,Version)])->bool {
+        !self.browsers.is_empty()&&self.browsers.iter().all(|(browser,version)|{
+            supported_since.iter().any(|(b,since)|b==browser&&version<since)
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_queries() {
+        let targets=Targets::parse("firefox >= 143, safari 15.4 or last 1 chrome version").unwrap();
+        assert_eq!(targets.browsers,[
+            (Browser::Firefox,Version(143,0)),(Browser::Firefox,Version(144,0)),
+            (Browser::Safari,Version(15,4)),(Browser::Chrome,Version(141,0)),
+        ]);
+        assert_eq!(Targets::parse("last 2 versions").unwrap().browsers.len(),Browser::ALL.len()*2);
+        assert!(matches!(Targets::parse("netscape 4"),Err(TargetsError::UnknownBrowser(_))));
+        assert!(matches!(Targets::parse("> 1%"),Err(TargetsError::UnsupportedQuery(_))));
+        assert!(matches!(Targets::parse("last two versions"),Err(TargetsError::InvalidQuery(_))));
+    }
+    #[test]
+    fn needs() {
+        let targets=Targets::parse("firefox 60, chrome 100").unwrap();
+        assert!(targets.needs(&[(Browser::Firefox,Version(62,0))]));
+        assert!(!targets.needs(&[(Browser::Firefox,Version(60,0)),(Browser::Safari,Version(20,0))]));
+        assert!(!Targets::default().needs(&[(Browser::Firefox,Version::NEVER)]));
+        assert!(!targets.needs_everywhere(&[(Browser::Firefox,Version(62,0))]));
+        assert!(targets.needs_everywhere(&[(Browser::Firefox,Version(62,0)),(Browser::Chrome,Version(101,0))]));
+    }
+}